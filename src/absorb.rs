@@ -0,0 +1,178 @@
+use crate::{
+    ansi::strip_ansi,
+    jj_commands::{DiffOptions, DiffRevisions, JjCommand, JjCommandError},
+    model::GlobalArgs,
+};
+use anyhow::anyhow;
+use regex::Regex;
+
+// Where a hunk of the source change would go
+#[derive(Debug, PartialEq, Eq)]
+pub enum AbsorbDestination {
+    Change(String),
+    OnlyAddsLines,
+    SeveralChanges,
+    Immutable,
+}
+
+#[derive(Debug)]
+pub struct AbsorbHunk {
+    pub path: String,
+    pub new_start: u32,
+    pub num_removed: u32,
+    pub num_added: u32,
+    pub destination: AbsorbDestination,
+}
+
+// Predicts what jj absorb would do, since it has no dry run. Like jj, each hunk goes to the mutable
+// ancestor that last modified all the lines it removes, so hunks that only add lines, or remove
+// lines from several changes, stay in the source. None if the source is a merge, since its lines
+// can't be annotated against a single parent.
+pub fn preview_absorb(
+    global_args: &GlobalArgs,
+    source: &str,
+    maybe_file_path: Option<&str>,
+) -> Result<Option<Vec<AbsorbHunk>>, JjCommandError> {
+    let parents = format!("{source}-");
+    let parents_output = JjCommand::change_ids(&parents, global_args.clone()).run()?;
+    if strip_ansi(&parents_output).lines().count() > 1 {
+        return Ok(None);
+    }
+
+    let source_revisions = DiffRevisions::Change(source.to_string());
+    let summary_output = JjCommand::diff_summary(&source_revisions, global_args.clone()).run()?;
+    // Added files have no lines to absorb, and renamed or copied files are annotated at their old
+    // path
+    let paths: Vec<(String, String)> = strip_ansi(&summary_output)
+        .lines()
+        .filter_map(parse_summary_paths)
+        .filter(|(_, new_path)| maybe_file_path.is_none_or(|file_path| file_path == new_path))
+        .collect();
+
+    let destinations_revset = format!("mutable() & ::({parents})");
+    let destinations_output =
+        JjCommand::change_ids(&destinations_revset, global_args.clone()).run()?;
+    let destinations: Vec<String> = strip_ansi(&destinations_output)
+        .lines()
+        .map(str::to_string)
+        .collect();
+
+    // Without context, each hunk is a single block of changed lines, as absorb sees them
+    let mut diff_global_args = global_args.clone();
+    diff_global_args.diff_options = DiffOptions {
+        context: Some(0),
+        ..DiffOptions::default()
+    };
+
+    let mut hunks = Vec::new();
+    for (old_path, path) in paths {
+        let diff_output =
+            JjCommand::diff_file(&source_revisions, &path, diff_global_args.clone()).run()?;
+        let annotate_output =
+            JjCommand::file_annotate(&parents, &old_path, global_args.clone()).run()?;
+        let line_change_ids: Vec<String> = strip_ansi(&annotate_output)
+            .lines()
+            .map(|line| line.split('\t').next().unwrap_or_default().to_string())
+            .collect();
+
+        for (old_start, num_removed, new_start, num_added) in parse_hunk_ranges(&diff_output)? {
+            let mut change_ids: Vec<&str> = (old_start..old_start + num_removed)
+                .filter_map(|line_num| line_change_ids.get(line_num as usize - 1))
+                .map(String::as_str)
+                .collect();
+            change_ids.dedup();
+
+            let destination = match change_ids[..] {
+                [] => AbsorbDestination::OnlyAddsLines,
+                [change_id] => match destinations.iter().find(|d| d.starts_with(change_id)) {
+                    Some(destination) => AbsorbDestination::Change(destination.clone()),
+                    None => AbsorbDestination::Immutable,
+                },
+                _ => AbsorbDestination::SeveralChanges,
+            };
+            hunks.push(AbsorbHunk {
+                path: path.clone(),
+                new_start,
+                num_removed,
+                num_added,
+                destination,
+            });
+        }
+    }
+
+    Ok(Some(hunks))
+}
+
+// Returns the old and new path of a modified, renamed or copied file in a diff summary, where
+// renames are shown like "src/{old.rs => new.rs}"
+fn parse_summary_paths(line: &str) -> Option<(String, String)> {
+    if let Some(path) = line.strip_prefix("M ") {
+        return Some((path.to_string(), path.to_string()));
+    }
+    let description = line
+        .strip_prefix("R ")
+        .or_else(|| line.strip_prefix("C "))?;
+    let re = Regex::new(r"^(.*)\{(.*?) => (.*?)\}(.*)$").unwrap();
+    let captures = re.captures(description)?;
+    let (start, old, new, end) = (&captures[1], &captures[2], &captures[3], &captures[4]);
+    // Moving a file up a directory leaves an empty side, so its doubled slash is dropped
+    let join = |middle: &str| format!("{start}{middle}{end}").replace("//", "/");
+    Some((join(old), join(new)))
+}
+
+// Returns the old start, old length, new start and new length of each hunk in a git diff
+fn parse_hunk_ranges(output: &str) -> Result<Vec<(u32, u32, u32, u32)>, JjCommandError> {
+    let re = Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").unwrap();
+    strip_ansi(output)
+        .lines()
+        .filter(|line| line.starts_with("@@"))
+        .map(|line| {
+            let captures = re.captures(line).ok_or_else(|| {
+                JjCommandError::new_other(anyhow!("Cannot parse hunk header: {line}"))
+            })?;
+            let num = |idx: usize| {
+                captures
+                    .get(idx)
+                    .map_or(Ok(1), |m| m.as_str().parse::<u32>())
+                    .map_err(JjCommandError::new_other)
+            };
+            Ok((num(1)?, num(2)?, num(3)?, num(4)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hunk_ranges_with_implicit_lengths() {
+        let output = "diff --git a/f b/f\n@@ -2 +2,2 @@\n-a\n+b\n+c\n@@ -10,0 +12 @@\n+d\n";
+        assert_eq!(
+            parse_hunk_ranges(output).unwrap(),
+            [(2, 1, 2, 2), (10, 0, 12, 1)]
+        );
+    }
+
+    #[test]
+    fn parses_summary_paths_of_renamed_and_copied_files() {
+        let paths = parse_summary_paths;
+        assert_eq!(
+            paths("M src/main.rs"),
+            Some(("src/main.rs".to_string(), "src/main.rs".to_string()))
+        );
+        assert_eq!(
+            paths("R src/{old.rs => new.rs}"),
+            Some(("src/old.rs".to_string(), "src/new.rs".to_string()))
+        );
+        assert_eq!(
+            paths("C {a => b}/lib.rs"),
+            Some(("a/lib.rs".to_string(), "b/lib.rs".to_string()))
+        );
+        assert_eq!(
+            paths("R src/{ => util}/io.rs"),
+            Some(("src/io.rs".to_string(), "src/util/io.rs".to_string()))
+        );
+        assert_eq!(paths("A src/main.rs"), None);
+    }
+}
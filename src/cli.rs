@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
 
 const DEFAULT_REVSET: &str = "root() | remote_bookmarks() | ancestors(immutable_heads().., 50)";
 
@@ -12,4 +13,48 @@ pub struct Args {
     /// Which revisions to show
     #[arg(short = 'r', long, value_name = "REVSETS", default_value = DEFAULT_REVSET)]
     pub revisions: String,
+
+    /// Destructive commands to run without asking for confirmation
+    #[arg(long, value_name = "COMMANDS", value_delimiter = ',')]
+    pub no_confirm: Vec<ConfirmCommand>,
+
+    /// Show diffs in jj's configured pager instead of the built-in viewer
+    #[arg(long)]
+    pub external_pager: bool,
+
+    /// Don't refresh the log when the repository is changed outside majjit
+    #[arg(long)]
+    pub no_auto_refresh: bool,
+
+    /// Also record the commands majjit runs to read the repo in the command log
+    #[arg(long)]
+    pub log_internal_commands: bool,
+
+    /// Append every jj invocation and its output to FILE, for replaying in tests
+    #[arg(long, value_name = "FILE")]
+    pub record_fixtures: Option<PathBuf>,
+
+    /// Run without a terminal, pressing the whitespace separated KEYS (like "j Tab C-r"),
+    /// then print the screen
+    #[arg(long, value_name = "KEYS")]
+    pub headless: Option<String>,
+
+    /// Screen size for --headless
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "120x40", value_parser = parse_size)]
+    pub headless_size: (u16, u16),
+}
+
+fn parse_size(size: &str) -> Result<(u16, u16), String> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, found {size:?}"))?;
+    let parse = |n: &str| n.parse::<u16>().map_err(|err| err.to_string());
+    Ok((parse(width)?, parse(height)?))
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmCommand {
+    Abandon,
+    Restore,
+    Push,
 }
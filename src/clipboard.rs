@@ -0,0 +1,63 @@
+use anyhow::Result;
+use std::io::{Write, stdout};
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Asks the terminal to set the clipboard with an OSC 52 sequence, which it relays from wherever
+// majjit runs, like over SSH, without needing a clipboard tool
+pub fn copy(text: &str) -> Result<()> {
+    let in_tmux = std::env::var_os("TMUX").is_some();
+    let mut stdout = stdout();
+    stdout.write_all(osc52_sequence(text, in_tmux).as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    if !in_tmux {
+        return sequence;
+    }
+    // tmux passes the sequence on to the outer terminal when it's wrapped like this, with escapes
+    // doubled
+    format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (idx, byte)| {
+            group | (*byte as u32) << (16 - 8 * idx)
+        });
+        // Each 3 bytes become 4 characters, padded with = when fewer bytes are left
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                let sextet = (group >> (18 - 6 * idx)) & 0x3f;
+                encoded.push(BASE64_CHARS[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_osc52_sequences() {
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(
+            osc52_sequence("qpvuntsm", false),
+            "\x1b]52;c;cXB2dW50c20=\x07"
+        );
+        assert_eq!(
+            osc52_sequence("qpvuntsm", true),
+            "\x1bPtmux;\x1b\x1b]52;c;cXB2dW50c20=\x07\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use ansi_to_tui::IntoText;
+use anyhow::Result;
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span, Text},
+};
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+// Oldest entries are dropped past this, since internal commands can pile up quickly
+const COMMAND_LOG_MAX_ENTRIES: usize = 200;
+
+#[derive(Debug)]
+pub struct CommandLogEntry {
+    pub args: Vec<String>,
+    // None if jj couldn't be run or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// History of the jj commands majjit has run, shared between all clones of the global args
+#[derive(Debug, Clone)]
+pub struct CommandLog {
+    entries: Rc<RefCell<Vec<CommandLogEntry>>>,
+    // Whether to also record commands majjit runs to read the repo, like log and diff
+    pub record_internal: bool,
+}
+
+impl CommandLog {
+    pub fn new(record_internal: bool) -> Self {
+        Self {
+            entries: Rc::new(RefCell::new(Vec::new())),
+            record_internal,
+        }
+    }
+
+    pub fn record(&self, entry: CommandLogEntry) {
+        let mut entries = self.entries.borrow_mut();
+        entries.push(entry);
+        if entries.len() > COMMAND_LOG_MAX_ENTRIES {
+            let excess = entries.len() - COMMAND_LOG_MAX_ENTRIES;
+            entries.drain(..excess);
+        }
+    }
+
+    pub fn to_text(&self) -> Result<Text<'static>> {
+        let entries = self.entries.borrow();
+        if entries.is_empty() {
+            return Ok(Text::from("No commands run yet"));
+        }
+
+        let mut lines = Vec::new();
+        for entry in entries.iter() {
+            let (status, status_color) = match entry.exit_code {
+                Some(0) => ("exit 0".to_string(), Color::Green),
+                Some(code) => (format!("exit {code}"), Color::Red),
+                None => ("no exit code".to_string(), Color::Red),
+            };
+            lines.push(Line::from(vec![
+                Span::styled("❯", Style::default().fg(Color::Yellow)),
+                Span::raw(" jj "),
+                Span::raw(entry.args.join(" ").replace('\n', "\\n")),
+                Span::raw("  "),
+                Span::styled(status, Style::default().fg(status_color)),
+                Span::styled(
+                    format!(" in {}ms", entry.duration.as_millis()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+            for output in [&entry.stdout, &entry.stderr] {
+                if !output.trim().is_empty() {
+                    lines.extend(output.trim_end().into_text()?.lines);
+                }
+            }
+            lines.push(Line::raw(""));
+        }
+        Ok(Text::from(lines))
+    }
+}
@@ -9,6 +9,17 @@ use std::collections::HashMap;
 
 type HelpEntries = IndexMap<String, Vec<(String, String)>>;
 
+const HELP_COL_WIDTH: usize = 26;
+
+// A command reachable through the tree, with the keys that run it
+#[derive(Debug, Clone)]
+pub struct CommandAction {
+    pub key_codes: Vec<KeyCode>,
+    pub group: String,
+    pub help: String,
+    pub message: Message,
+}
+
 #[derive(Debug, Clone)]
 pub struct CommandTreeNodeChildren {
     nodes: HashMap<KeyCode, CommandTreeNode>,
@@ -46,6 +57,47 @@ impl CommandTreeNodeChildren {
         render_help_text(entries)
     }
 
+    // The key of the entry shown at a line and column of the help text
+    pub fn get_help_key_at(&self, line: usize, column: usize) -> Option<KeyCode> {
+        let group_idx = column.checked_sub(1)? / HELP_COL_WIDTH;
+        let entries = self.get_help_entries();
+        let (_, help_group) = entries.get_index(group_idx)?;
+        // The first line of each column is the group's title
+        let (key, _) = help_group.get(line.checked_sub(1)?)?;
+        self.find_key_code(key)
+    }
+
+    fn find_key_code(&self, key: &str) -> Option<KeyCode> {
+        self.nodes
+            .keys()
+            .find(|key_code| key_code.to_string() == key)
+            .copied()
+    }
+
+    // In the order they appear in the help
+    fn collect_actions(&self, key_codes: &[KeyCode], actions: &mut Vec<CommandAction>) {
+        for (group, help_group) in self.get_help_entries() {
+            for (key, help) in help_group {
+                let Some(key_code) = self.find_key_code(&key) else {
+                    continue;
+                };
+                let mut key_codes = key_codes.to_vec();
+                key_codes.push(key_code);
+                match &self.nodes[&key_code] {
+                    CommandTreeNode::Children(children) => {
+                        children.collect_actions(&key_codes, actions)
+                    }
+                    CommandTreeNode::Action(message) => actions.push(CommandAction {
+                        key_codes,
+                        group: group.clone(),
+                        help,
+                        message: *message,
+                    }),
+                }
+            }
+        }
+    }
+
     pub fn add_child(
         &mut self,
         help_group_text: &str,
@@ -94,6 +146,19 @@ impl CommandTree {
         }
     }
 
+    pub fn get_children(&self, key_codes: &[KeyCode]) -> Option<&CommandTreeNodeChildren> {
+        match self.get_node(key_codes)? {
+            CommandTreeNode::Action(_) => None,
+            CommandTreeNode::Children(children) => Some(children),
+        }
+    }
+
+    pub fn get_actions(&self) -> Vec<CommandAction> {
+        let mut actions = Vec::new();
+        self.children().collect_actions(&[], &mut actions);
+        actions
+    }
+
     pub fn get_node(&self, key_codes: &[KeyCode]) -> Option<&CommandTreeNode> {
         let mut node = &self.0;
 
@@ -134,6 +199,7 @@ impl CommandTree {
             ("h/🠈 ", "Prev sibling"),
             ("K", "Select parent"),
             ("@", "Select @ change"),
+            ("m", "Mark change"),
         ]
         .iter()
         .map(|(key, help)| (key.to_string(), help.to_string()))
@@ -143,6 +209,10 @@ impl CommandTree {
             ("Ctrl-r", "Refresh log tree"),
             ("Esc", "Clear app state"),
             ("i", "Toggle --ignore-immutable"),
+            ("p", "Toggle preview pane"),
+            ("o", "Cycle pane focus"),
+            ("z", "Maximise output panel"),
+            ("$", "Show command log"),
             ("?", "Show help"),
             ("q", "Quit"),
         ]
@@ -170,6 +240,18 @@ impl CommandTree {
                 vec![KeyCode::Char('a'), KeyCode::Char('a')],
                 CommandTreeNode::Action(Message::Abandon),
             ),
+            (
+                "Commands",
+                "Absorb into ancestors",
+                vec![KeyCode::Char('A')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Absorb",
+                "Selected change or file",
+                vec![KeyCode::Char('A'), KeyCode::Char('A')],
+                CommandTreeNode::Action(Message::Absorb),
+            ),
             (
                 "Commands",
                 "Bookmark",
@@ -200,6 +282,54 @@ impl CommandTree {
                 vec![KeyCode::Char('c'), KeyCode::Char('c')],
                 CommandTreeNode::Action(Message::Commit),
             ),
+            (
+                "Commit",
+                "@ inline",
+                vec![KeyCode::Char('c'), KeyCode::Char('i')],
+                CommandTreeNode::Action(Message::CommitInline),
+            ),
+            (
+                "Commit",
+                "One-line message",
+                vec![KeyCode::Char('c'), KeyCode::Char('m')],
+                CommandTreeNode::Action(Message::CommitQuick),
+            ),
+            (
+                "Commands",
+                "Compare revisions",
+                vec![KeyCode::Char('C')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Compare",
+                "Marked to selected change",
+                vec![KeyCode::Char('C'), KeyCode::Char('c')],
+                CommandTreeNode::Action(Message::CompareMarked),
+            ),
+            (
+                "Compare",
+                "trunk() to working copy",
+                vec![KeyCode::Char('C'), KeyCode::Char('t')],
+                CommandTreeNode::Action(Message::CompareTrunk),
+            ),
+            (
+                "Compare",
+                "Bookmark remote to selected",
+                vec![KeyCode::Char('C'), KeyCode::Char('r')],
+                CommandTreeNode::Action(Message::CompareRemote),
+            ),
+            (
+                "Compare",
+                "Stack to older operation",
+                vec![KeyCode::Char('C'), KeyCode::Char('o')],
+                CommandTreeNode::Action(Message::CompareOperation),
+            ),
+            (
+                "Compare",
+                "Close comparisons",
+                vec![KeyCode::Char('C'), KeyCode::Char('x')],
+                CommandTreeNode::Action(Message::CloseComparison),
+            ),
             (
                 "Commands",
                 "Describe change",
@@ -212,6 +342,66 @@ impl CommandTree {
                 vec![KeyCode::Char('d'), KeyCode::Char('d')],
                 CommandTreeNode::Action(Message::Describe),
             ),
+            (
+                "Describe",
+                "Selected change inline",
+                vec![KeyCode::Char('d'), KeyCode::Char('i')],
+                CommandTreeNode::Action(Message::DescribeInline),
+            ),
+            (
+                "Describe",
+                "One-line message",
+                vec![KeyCode::Char('d'), KeyCode::Char('m')],
+                CommandTreeNode::Action(Message::DescribeQuick),
+            ),
+            (
+                "Commands",
+                "Diff options",
+                vec![KeyCode::Char('D')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Diff options",
+                "Toggle git format",
+                vec![KeyCode::Char('D'), KeyCode::Char('g')],
+                CommandTreeNode::Action(Message::ToggleDiffFormat),
+            ),
+            (
+                "Diff options",
+                "More context lines",
+                vec![KeyCode::Char('D'), KeyCode::Char('+')],
+                CommandTreeNode::Action(Message::IncreaseDiffContext),
+            ),
+            (
+                "Diff options",
+                "Fewer context lines",
+                vec![KeyCode::Char('D'), KeyCode::Char('-')],
+                CommandTreeNode::Action(Message::DecreaseDiffContext),
+            ),
+            (
+                "Diff options",
+                "Ignore all space",
+                vec![KeyCode::Char('D'), KeyCode::Char('w')],
+                CommandTreeNode::Action(Message::ToggleIgnoreAllSpace),
+            ),
+            (
+                "Diff options",
+                "Ignore space change",
+                vec![KeyCode::Char('D'), KeyCode::Char('b')],
+                CommandTreeNode::Action(Message::ToggleIgnoreSpaceChange),
+            ),
+            (
+                "Diff options",
+                "Side-by-side for file",
+                vec![KeyCode::Char('D'), KeyCode::Char('s')],
+                CommandTreeNode::Action(Message::ToggleSideBySide),
+            ),
+            (
+                "Diff options",
+                "Reset to defaults",
+                vec![KeyCode::Char('D'), KeyCode::Char('r')],
+                CommandTreeNode::Action(Message::ResetDiffOptions),
+            ),
             (
                 "Commands",
                 "Edit change",
@@ -224,6 +414,54 @@ impl CommandTree {
                 vec![KeyCode::Char('e'), KeyCode::Char('e')],
                 CommandTreeNode::Action(Message::Edit),
             ),
+            (
+                "Commands",
+                "Duplicate change",
+                vec![KeyCode::Char('x')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Duplicate",
+                "Selected change in place",
+                vec![KeyCode::Char('x'), KeyCode::Char('x')],
+                CommandTreeNode::Action(Message::Duplicate),
+            ),
+            (
+                "Duplicate",
+                "Onto revision",
+                vec![KeyCode::Char('x'), KeyCode::Char('d')],
+                CommandTreeNode::Action(Message::DuplicateOnto),
+            ),
+            (
+                "Duplicate",
+                "After revision",
+                vec![KeyCode::Char('x'), KeyCode::Char('a')],
+                CommandTreeNode::Action(Message::DuplicateAfter),
+            ),
+            (
+                "Duplicate",
+                "Before revision",
+                vec![KeyCode::Char('x'), KeyCode::Char('b')],
+                CommandTreeNode::Action(Message::DuplicateBefore),
+            ),
+            (
+                "Commands",
+                "File commands",
+                vec![KeyCode::Char('f')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "File",
+                "Annotate selected file",
+                vec![KeyCode::Char('f'), KeyCode::Char('a')],
+                CommandTreeNode::Action(Message::FileAnnotate),
+            ),
+            (
+                "File",
+                "Open in editor at line",
+                vec![KeyCode::Char('f'), KeyCode::Char('e')],
+                CommandTreeNode::Action(Message::OpenInEditor),
+            ),
             (
                 "Commands",
                 "Git commands",
@@ -260,6 +498,78 @@ impl CommandTree {
                 vec![KeyCode::Char('n'), KeyCode::Char('b')],
                 CommandTreeNode::Action(Message::NewBefore),
             ),
+            (
+                "Commands",
+                "Next child of @",
+                vec![KeyCode::Char(']')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Next",
+                "New change on child",
+                vec![KeyCode::Char(']'), KeyCode::Char(']')],
+                CommandTreeNode::Action(Message::Next { edit: false }),
+            ),
+            (
+                "Next",
+                "Edit child",
+                vec![KeyCode::Char(']'), KeyCode::Char('e')],
+                CommandTreeNode::Action(Message::Next { edit: true }),
+            ),
+            (
+                "Next",
+                "New change on nth child",
+                vec![KeyCode::Char(']'), KeyCode::Char('c')],
+                CommandTreeNode::Action(Message::NextBy { edit: false }),
+            ),
+            (
+                "Next",
+                "Edit nth child",
+                vec![KeyCode::Char(']'), KeyCode::Char('C')],
+                CommandTreeNode::Action(Message::NextBy { edit: true }),
+            ),
+            (
+                "Commands",
+                "Prev parent of @",
+                vec![KeyCode::Char('[')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Prev",
+                "New change on parent",
+                vec![KeyCode::Char('['), KeyCode::Char('[')],
+                CommandTreeNode::Action(Message::Prev { edit: false }),
+            ),
+            (
+                "Prev",
+                "Edit parent",
+                vec![KeyCode::Char('['), KeyCode::Char('e')],
+                CommandTreeNode::Action(Message::Prev { edit: true }),
+            ),
+            (
+                "Prev",
+                "New change on nth parent",
+                vec![KeyCode::Char('['), KeyCode::Char('c')],
+                CommandTreeNode::Action(Message::PrevBy { edit: false }),
+            ),
+            (
+                "Prev",
+                "Edit nth parent",
+                vec![KeyCode::Char('['), KeyCode::Char('C')],
+                CommandTreeNode::Action(Message::PrevBy { edit: true }),
+            ),
+            (
+                "Commands",
+                "Parallelize changes",
+                vec![KeyCode::Char('P')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Parallelize",
+                "Marked to selected change",
+                vec![KeyCode::Char('P'), KeyCode::Char('P')],
+                CommandTreeNode::Action(Message::Parallelize),
+            ),
             (
                 "Commands",
                 "Restore change",
@@ -268,7 +578,7 @@ impl CommandTree {
             ),
             (
                 "Restore",
-                "Selected change",
+                "Selected change/version",
                 vec![KeyCode::Char('r'), KeyCode::Char('r')],
                 CommandTreeNode::Action(Message::Restore),
             ),
@@ -296,6 +606,78 @@ impl CommandTree {
                 vec![KeyCode::Char('u'), KeyCode::Char('u')],
                 CommandTreeNode::Action(Message::Undo),
             ),
+            (
+                "Commands",
+                "Yank to clipboard",
+                vec![KeyCode::Char('y')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Yank",
+                "Change id",
+                vec![KeyCode::Char('y'), KeyCode::Char('c')],
+                CommandTreeNode::Action(Message::YankChangeId),
+            ),
+            (
+                "Yank",
+                "Commit id",
+                vec![KeyCode::Char('y'), KeyCode::Char('i')],
+                CommandTreeNode::Action(Message::YankCommitId),
+            ),
+            (
+                "Yank",
+                "File path",
+                vec![KeyCode::Char('y'), KeyCode::Char('f')],
+                CommandTreeNode::Action(Message::YankFilePath),
+            ),
+            (
+                "Yank",
+                "Description",
+                vec![KeyCode::Char('y'), KeyCode::Char('d')],
+                CommandTreeNode::Action(Message::YankDescription),
+            ),
+            (
+                "Yank",
+                "Hunk or dragged lines",
+                vec![KeyCode::Char('y'), KeyCode::Char('h')],
+                CommandTreeNode::Action(Message::YankHunk),
+            ),
+            (
+                "Yank",
+                "File patch",
+                vec![KeyCode::Char('y'), KeyCode::Char('p')],
+                CommandTreeNode::Action(Message::YankFilePatch),
+            ),
+            (
+                "Commands",
+                "Revert change",
+                vec![KeyCode::Char('V')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Revert",
+                "Selected change onto @",
+                vec![KeyCode::Char('V'), KeyCode::Char('V')],
+                CommandTreeNode::Action(Message::Revert),
+            ),
+            (
+                "Revert",
+                "Onto revision",
+                vec![KeyCode::Char('V'), KeyCode::Char('d')],
+                CommandTreeNode::Action(Message::RevertOnto),
+            ),
+            (
+                "Revert",
+                "After revision",
+                vec![KeyCode::Char('V'), KeyCode::Char('a')],
+                CommandTreeNode::Action(Message::RevertAfter),
+            ),
+            (
+                "Revert",
+                "Before revision",
+                vec![KeyCode::Char('V'), KeyCode::Char('b')],
+                CommandTreeNode::Action(Message::RevertBefore),
+            ),
         ];
 
         let mut tree = Self(CommandTreeNode::new_children());
@@ -305,15 +687,13 @@ impl CommandTree {
 }
 
 fn render_help_text(entries: HelpEntries) -> Text<'static> {
-    const COL_WIDTH: usize = 26;
-
     // Get lines for each column
     let columns: Vec<Vec<Line>> = entries
         .into_iter()
         .map(|(group_help_text, help_group)| {
             let mut col_lines = Vec::new();
             col_lines.push(Line::from(vec![Span::styled(
-                format!("{group_help_text:COL_WIDTH$}"),
+                format!("{group_help_text:HELP_COL_WIDTH$}"),
                 Style::default().fg(Color::Blue),
             )]));
             col_lines.extend(
@@ -324,7 +704,7 @@ fn render_help_text(entries: HelpEntries) -> Text<'static> {
                         if !key.is_ascii() {
                             num_cols -= 3;
                         }
-                        let padding = " ".repeat(COL_WIDTH.saturating_sub(num_cols));
+                        let padding = " ".repeat(HELP_COL_WIDTH.saturating_sub(num_cols));
                         Line::from(vec![
                             Span::styled(key, Style::default().fg(Color::Green)),
                             Span::raw(" "),
@@ -345,7 +725,7 @@ fn render_help_text(entries: HelpEntries) -> Text<'static> {
             let mut spans: Vec<Span> = vec![Span::raw(" ")];
 
             for col in &columns {
-                let empty_line = Line::from(Span::raw(" ".repeat(COL_WIDTH)));
+                let empty_line = Line::from(Span::raw(" ".repeat(HELP_COL_WIDTH)));
                 let col_line = col.get(i).unwrap_or(&empty_line).clone();
                 spans.extend(col_line.spans)
             }
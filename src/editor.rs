@@ -0,0 +1,117 @@
+use crate::terminal::{self, Term};
+use anyhow::{Result, anyhow};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+// Hands the terminal over to the user's editor until it exits
+pub fn open_at_line(path: &Path, line_num: u32, term: Term) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let (program, args) = editor_command(&editor, &path.to_string_lossy(), line_num)
+        .ok_or_else(|| anyhow!("No editor set in $VISUAL or $EDITOR"))?;
+
+    terminal::relinquish_terminal()?;
+    let result = Command::new(&program).args(&args).status();
+    terminal::takeover_terminal(term)?;
+
+    let status = result.map_err(|err| anyhow!("Cannot run {program}: {err}"))?;
+    if !status.success() {
+        return Err(anyhow!("{program} exited with {status}"));
+    }
+    Ok(())
+}
+
+// Copies of files at other revisions are kept until majjit exits, since editors like "code" without
+// --wait return before they've read the file
+pub fn write_read_only_copy(revision: &str, file_path: &str, content: &str) -> io::Result<PathBuf> {
+    let dir = read_only_copies_dir().join(revision);
+    // Keeps the file's name, so the editor still picks the right syntax highlighting
+    let file_name = Path::new(file_path).file_name().unwrap_or_default();
+    let path = dir.join(file_name);
+    fs::create_dir_all(&dir)?;
+    // An earlier copy is read-only, and the revision may have been rewritten since
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    fs::write(&path, content)?;
+    let mut permissions = fs::metadata(&path)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&path, permissions)?;
+    Ok(path)
+}
+
+pub fn remove_read_only_copies() {
+    fs::remove_dir_all(read_only_copies_dir()).ok();
+}
+
+fn read_only_copies_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("majjit-{}", process::id()))
+}
+
+// The editor may come with its own args, like "code --wait". Most editors take the line as +N, but
+// a few want it appended to the path.
+fn editor_command(editor: &str, path: &str, line_num: u32) -> Option<(String, Vec<String>)> {
+    let mut words = editor.split_whitespace().map(str::to_string);
+    let program = words.next()?;
+    let mut args: Vec<String> = words.collect();
+
+    let name = Path::new(&program)
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    match name.as_str() {
+        "code" | "codium" => args.extend(["--goto".to_string(), format!("{path}:{line_num}")]),
+        "hx" | "helix" | "subl" | "zed" => args.push(format!("{path}:{line_num}")),
+        _ => args.extend([format!("+{line_num}"), path.to_string()]),
+    }
+    Some((program, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_the_line_the_way_each_editor_expects() {
+        let command = |editor| editor_command(editor, "src/main.rs", 12).unwrap();
+        assert_eq!(
+            command("nvim"),
+            (
+                "nvim".to_string(),
+                vec!["+12".to_string(), "src/main.rs".to_string()]
+            )
+        );
+        assert_eq!(
+            command("/usr/bin/code --wait"),
+            (
+                "/usr/bin/code".to_string(),
+                vec![
+                    "--wait".to_string(),
+                    "--goto".to_string(),
+                    "src/main.rs:12".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            command("hx"),
+            ("hx".to_string(), vec!["src/main.rs:12".to_string()])
+        );
+        assert_eq!(editor_command(" ", "src/main.rs", 12), None);
+    }
+
+    #[test]
+    fn read_only_copies_are_replaced_when_opened_again() {
+        let path = write_read_only_copy("qpvuntsm", "src/hello.txt", "hello\n").unwrap();
+        assert!(path.ends_with("qpvuntsm/hello.txt"));
+        assert!(fs::metadata(&path).unwrap().permissions().readonly());
+
+        let path = write_read_only_copy("qpvuntsm", "src/hello.txt", "hello world\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world\n");
+
+        remove_read_only_copies();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    ansi::strip_non_style_ansi,
+    terminal::{self, Term},
+};
+use anyhow::{Context, Result, anyhow};
+use std::{
+    fmt::Debug,
+    fs::OpenOptions,
+    io::{Read, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+pub const FIXTURE_COMMAND_MARKER: &str = ">>> jj";
+pub const FIXTURE_EXIT_MARKER: &str = ">>> exit ";
+pub const FIXTURE_STDOUT_MARKER: &str = ">>> stdout";
+pub const FIXTURE_STDERR_MARKER: &str = ">>> stderr";
+pub const FIXTURE_END_MARKER: &str = ">>> end";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutorOutput {
+    // None if jj was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Runs jj for a JjCommand. Global flags like --repository are kept apart from the command's own
+// args so fakes can match on the latter.
+pub trait JjExecutor: Debug {
+    fn run(&self, global_flags: &[String], args: &[String]) -> Result<ExecutorOutput>;
+
+    // Hands the terminal over to jj, so only stderr is captured
+    fn run_interactive(
+        &self,
+        global_flags: &[String],
+        args: &[String],
+        term: Term,
+    ) -> Result<ExecutorOutput>;
+}
+
+#[derive(Debug)]
+pub struct ProcessExecutor;
+
+impl JjExecutor for ProcessExecutor {
+    fn run(&self, global_flags: &[String], args: &[String]) -> Result<ExecutorOutput> {
+        let output = Command::new("jj").args(global_flags).args(args).output()?;
+        Ok(ExecutorOutput {
+            exit_code: output.status.code(),
+            stdout: String::from_utf8(output.stdout)?,
+            stderr: String::from_utf8_lossy(&output.stderr).into(),
+        })
+    }
+
+    fn run_interactive(
+        &self,
+        global_flags: &[String],
+        args: &[String],
+        term: Term,
+    ) -> Result<ExecutorOutput> {
+        let mut command = Command::new("jj");
+        command.args(global_flags).args(args).stderr(Stdio::piped());
+
+        terminal::relinquish_terminal()?;
+
+        let mut child = command.spawn()?;
+        let status = child.wait()?;
+
+        let mut stderr = String::new();
+        child
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow!("No stderr"))?
+            .read_to_string(&mut stderr)?;
+
+        terminal::takeover_terminal(term)?;
+
+        Ok(ExecutorOutput {
+            exit_code: status.code(),
+            stdout: String::new(),
+            stderr: strip_non_style_ansi(&stderr),
+        })
+    }
+}
+
+// Runs jj for real and appends every invocation to a fixture file for FixtureExecutor
+#[derive(Debug)]
+pub struct RecordingExecutor {
+    fixture_path: PathBuf,
+}
+
+impl RecordingExecutor {
+    pub fn new(fixture_path: PathBuf) -> Self {
+        Self { fixture_path }
+    }
+
+    fn record(&self, args: &[String], output: &ExecutorOutput) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.fixture_path)
+            .with_context(|| format!("Cannot open fixture file {:?}", self.fixture_path))?;
+        file.write_all(serialize_fixture(args, output).as_bytes())?;
+        Ok(())
+    }
+}
+
+impl JjExecutor for RecordingExecutor {
+    fn run(&self, global_flags: &[String], args: &[String]) -> Result<ExecutorOutput> {
+        let output = ProcessExecutor.run(global_flags, args)?;
+        self.record(args, &output)?;
+        Ok(output)
+    }
+
+    fn run_interactive(
+        &self,
+        global_flags: &[String],
+        args: &[String],
+        term: Term,
+    ) -> Result<ExecutorOutput> {
+        let output = ProcessExecutor.run_interactive(global_flags, args, term)?;
+        self.record(args, &output)?;
+        Ok(output)
+    }
+}
+
+// Args go one per line, with newlines and backslashes escaped
+pub fn serialize_fixture(args: &[String], output: &ExecutorOutput) -> String {
+    let mut fixture = format!("{FIXTURE_COMMAND_MARKER}\n");
+    for arg in args {
+        fixture.push_str(&arg.replace('\\', "\\\\").replace('\n', "\\n"));
+        fixture.push('\n');
+    }
+    let exit_code = output
+        .exit_code
+        .map_or("signal".to_string(), |code| code.to_string());
+    fixture.push_str(&format!("{FIXTURE_EXIT_MARKER}{exit_code}\n"));
+    for (marker, content) in [
+        (FIXTURE_STDOUT_MARKER, &output.stdout),
+        (FIXTURE_STDERR_MARKER, &output.stderr),
+    ] {
+        fixture.push_str(marker);
+        fixture.push('\n');
+        fixture.push_str(content);
+        if !content.is_empty() && !content.ends_with('\n') {
+            fixture.push('\n');
+        }
+    }
+    fixture.push_str(FIXTURE_END_MARKER);
+    fixture.push('\n');
+    fixture
+}
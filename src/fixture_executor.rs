@@ -0,0 +1,209 @@
+use crate::{
+    executor::{
+        ExecutorOutput, FIXTURE_COMMAND_MARKER, FIXTURE_END_MARKER, FIXTURE_EXIT_MARKER,
+        FIXTURE_STDERR_MARKER, FIXTURE_STDOUT_MARKER, JjExecutor,
+    },
+    terminal::Term,
+};
+use anyhow::{Context, Result, anyhow};
+use std::{cell::RefCell, fs, path::Path};
+
+#[derive(Debug, Clone)]
+struct Fixture {
+    args: Vec<String>,
+    output: ExecutorOutput,
+}
+
+impl Fixture {
+    fn parse_all(fixtures: &str) -> Result<Vec<Self>> {
+        let mut lines = fixtures.lines();
+        let mut parsed = Vec::new();
+
+        while let Some(line) = lines.next() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line != FIXTURE_COMMAND_MARKER {
+                return Err(anyhow!(
+                    "Expected {FIXTURE_COMMAND_MARKER:?}, found {line:?}"
+                ));
+            }
+
+            let mut args = Vec::new();
+            let exit_code = loop {
+                let line = lines
+                    .next()
+                    .ok_or_else(|| anyhow!("Fixture ended before exit code"))?;
+                if let Some(exit_code) = line.strip_prefix(FIXTURE_EXIT_MARKER) {
+                    break exit_code.parse::<i32>().ok();
+                }
+                args.push(unescape_arg(line));
+            };
+
+            let mut stdout = String::new();
+            let mut stderr = String::new();
+            let mut current_output = None;
+            loop {
+                let line = lines
+                    .next()
+                    .ok_or_else(|| anyhow!("Fixture ended before {FIXTURE_END_MARKER:?}"))?;
+                match line {
+                    FIXTURE_STDOUT_MARKER => current_output = Some(&mut stdout),
+                    FIXTURE_STDERR_MARKER => current_output = Some(&mut stderr),
+                    FIXTURE_END_MARKER => break,
+                    _ => {
+                        let output = current_output
+                            .as_mut()
+                            .ok_or_else(|| anyhow!("Fixture output outside of a section"))?;
+                        output.push_str(line);
+                        output.push('\n');
+                    }
+                }
+            }
+
+            parsed.push(Self {
+                args,
+                output: ExecutorOutput {
+                    exit_code,
+                    stdout,
+                    stderr,
+                },
+            });
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn unescape_arg(arg: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// Serves canned outputs instead of running jj. Fixtures for the same args are served in order,
+// with the last one repeated, so a test can see the log change after running a command.
+#[derive(Debug, Default)]
+pub struct FixtureExecutor {
+    fixtures: RefCell<Vec<Fixture>>,
+    calls: RefCell<Vec<Vec<String>>>,
+}
+
+impl FixtureExecutor {
+    pub fn from_fixtures(fixtures: &str) -> Result<Self> {
+        Ok(Self {
+            fixtures: RefCell::new(Fixture::parse_all(fixtures)?),
+            calls: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let fixtures = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read fixture file {path:?}"))?;
+        Self::from_fixtures(&fixtures)
+    }
+
+    // Global flags and args of every command run so far
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.borrow().clone()
+    }
+}
+
+impl JjExecutor for FixtureExecutor {
+    fn run(&self, global_flags: &[String], args: &[String]) -> Result<ExecutorOutput> {
+        self.calls
+            .borrow_mut()
+            .push(global_flags.iter().chain(args).cloned().collect());
+
+        let mut fixtures = self.fixtures.borrow_mut();
+        let matching: Vec<usize> = fixtures
+            .iter()
+            .enumerate()
+            .filter(|(_, fixture)| fixture.args == args)
+            .map(|(idx, _)| idx)
+            .collect();
+        match matching.as_slice() {
+            [] => Err(anyhow!("No fixture for: jj {}", args.join(" "))),
+            [idx] => Ok(fixtures[*idx].output.clone()),
+            [idx, ..] => Ok(fixtures.remove(*idx).output),
+        }
+    }
+
+    fn run_interactive(
+        &self,
+        global_flags: &[String],
+        args: &[String],
+        _term: Term,
+    ) -> Result<ExecutorOutput> {
+        self.run(global_flags, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::serialize_fixture;
+
+    #[test]
+    fn serialized_fixtures_parse_back() {
+        let args = vec![
+            "describe".to_string(),
+            "--message".to_string(),
+            "Summary\n\nPath C:\\new".to_string(),
+        ];
+        let output = ExecutorOutput {
+            exit_code: Some(0),
+            stdout: String::new(),
+            stderr: "Working copy now at: abc\n".to_string(),
+        };
+
+        let fixtures = Fixture::parse_all(&serialize_fixture(&args, &output)).unwrap();
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(fixtures[0].args, args);
+        assert_eq!(fixtures[0].output, output);
+    }
+
+    #[test]
+    fn repeated_fixtures_are_served_in_order() {
+        let fixtures = [
+            serialize_fixture(
+                &["log".to_string()],
+                &ExecutorOutput {
+                    exit_code: Some(0),
+                    stdout: "first\n".to_string(),
+                    stderr: String::new(),
+                },
+            ),
+            serialize_fixture(
+                &["log".to_string()],
+                &ExecutorOutput {
+                    exit_code: Some(0),
+                    stdout: "second\n".to_string(),
+                    stderr: String::new(),
+                },
+            ),
+        ]
+        .concat();
+        let executor = FixtureExecutor::from_fixtures(&fixtures).unwrap();
+        let args = ["log".to_string()];
+
+        assert_eq!(executor.run(&[], &args).unwrap().stdout, "first\n");
+        assert_eq!(executor.run(&[], &args).unwrap().stdout, "second\n");
+        assert_eq!(executor.run(&[], &args).unwrap().stdout, "second\n");
+        assert!(executor.run(&[], &["status".to_string()]).is_err());
+        assert_eq!(executor.calls().len(), 4);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    model::{Model, State},
+    terminal::{self, Term},
+    update::update_with_event,
+    view::view,
+};
+use anyhow::{Result, anyhow};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{Terminal, backend::TestBackend};
+
+// Drives the TUI with scripted keys, rendering to an in-memory buffer instead of a terminal
+pub struct Headless {
+    model: Model,
+    terminal: Terminal<TestBackend>,
+    detached_term: Term,
+}
+
+impl Headless {
+    pub fn new(mut model: Model, width: u16, height: u16) -> Result<Self> {
+        model.set_headless();
+        let mut headless = Self {
+            model,
+            terminal: Terminal::new(TestBackend::new(width, height))?,
+            detached_term: terminal::detached_terminal()?,
+        };
+        headless.draw()?;
+        Ok(headless)
+    }
+
+    // Keys are separated by whitespace, see parse_key for the syntax
+    pub fn run_script(&mut self, script: &str) -> Result<()> {
+        for key in script.split_whitespace().map(parse_key) {
+            if !self.is_running() {
+                break;
+            }
+            self.press(key?)?;
+        }
+        Ok(())
+    }
+
+    pub fn press(&mut self, key: KeyEvent) -> Result<()> {
+        update_with_event(
+            self.detached_term.clone(),
+            &mut self.model,
+            Some(Event::Key(key)),
+        )?;
+        self.draw()?;
+
+        // Queued commands only run on the update after the one that queued them
+        update_with_event(self.detached_term.clone(), &mut self.model, None)?;
+        self.draw()
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        self.terminal.draw(|f| view(&mut self.model, f))?;
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.model.state != State::Quit
+    }
+
+    // The rendered buffer as plain text, with trailing spaces trimmed from each row
+    pub fn screen(&self) -> String {
+        let buffer = self.terminal.backend().buffer();
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                let row: String = (area.left()..area.right())
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect();
+                row.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// A single character, a key name like Enter or PageDown, or either prefixed with C- for Ctrl
+fn parse_key(token: &str) -> Result<KeyEvent> {
+    if let Some(key) = token.strip_prefix("C-")
+        && !key.is_empty()
+    {
+        let key = parse_key(key)?;
+        return Ok(KeyEvent::new(key.code, KeyModifiers::CONTROL));
+    }
+
+    let code = match token {
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "Esc" => KeyCode::Esc,
+        "Space" => KeyCode::Char(' '),
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        _ => {
+            let mut chars = token.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return Err(anyhow!("Unknown key in script: {token:?}")),
+            }
+        }
+    };
+    Ok(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        command_log::CommandLog,
+        executor::{JjExecutor, ProcessExecutor},
+        fixture_executor::FixtureExecutor,
+        jj_commands::JjCommand,
+        model::GlobalArgs,
+    };
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+        rc::Rc,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    fn headless(repository: String, executor: Rc<dyn JjExecutor>, revset: &str) -> Headless {
+        let global_args = GlobalArgs::new(repository, executor, CommandLog::new(false));
+        let model = Model::new(global_args, revset.to_string(), Vec::new(), false).unwrap();
+        Headless::new(model, 120, 40).unwrap()
+    }
+
+    fn jj(repository: &Path, args: &[&str]) {
+        let status = Command::new("jj")
+            .args([
+                "--config",
+                "user.name=Test",
+                "--config",
+                "user.email=test@example.com",
+            ])
+            .arg("--repository")
+            .arg(repository)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "jj {args:?} failed");
+    }
+
+    fn temp_repo() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("majjit-test-{}-{nanos}", std::process::id()));
+        let status = Command::new("jj")
+            .args(["git", "init"])
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success(), "jj git init failed");
+        path
+    }
+
+    #[test]
+    fn parses_key_scripts() {
+        assert_eq!(parse_key("j").unwrap().code, KeyCode::Char('j'));
+        assert_eq!(parse_key("PageDown").unwrap().code, KeyCode::PageDown);
+        assert_eq!(
+            parse_key("C-r").unwrap(),
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parse_key("C-").unwrap_err().to_string(),
+            r#"Unknown key in script: "C-""#
+        );
+    }
+
+    #[test]
+    fn renders_fixture_log_and_help() {
+        let fixture_path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/two_commits.txt");
+        let executor = Rc::new(FixtureExecutor::from_file(&fixture_path).unwrap());
+        let mut headless = headless("/repo".to_string(), executor, "all()");
+
+        let screen = headless.screen();
+        assert!(screen.contains("repository: /repo"));
+        assert!(screen.contains("qpvuntsm"));
+        assert!(screen.contains("modified  hello.txt"));
+
+        headless.run_script("?").unwrap();
+        assert!(headless.screen().contains("Navigation"));
+
+        headless.run_script("Esc q").unwrap();
+        assert!(!headless.is_running());
+    }
+
+    #[test]
+    fn refuses_commands_that_need_a_terminal() {
+        let fixture_path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/two_commits.txt");
+        let executor = Rc::new(FixtureExecutor::from_file(&fixture_path).unwrap());
+        let mut headless = headless("/repo".to_string(), executor.clone(), "all()");
+
+        headless.run_script("d d").unwrap();
+        assert!(headless.screen().contains("can't run headless"));
+        assert!(
+            !executor
+                .calls()
+                .iter()
+                .any(|call| call.contains(&"describe".to_string()))
+        );
+    }
+
+    #[test]
+    #[ignore = "needs jj"]
+    fn creates_and_describes_changes_in_a_real_repo() {
+        let repo_path = temp_repo();
+        fs::write(repo_path.join("hello.txt"), "hello\n").unwrap();
+        jj(&repo_path, &["describe", "--message", "Add greeting"]);
+
+        let repository =
+            JjCommand::ensure_valid_repo(repo_path.to_str().unwrap(), &ProcessExecutor).unwrap();
+        let mut headless = headless(repository, Rc::new(ProcessExecutor), "all()");
+        let screen = headless.screen();
+        assert!(screen.contains("Add greeting"));
+        assert!(screen.contains("new file  hello.txt"));
+
+        // New change on top of the selected one, then give it a description
+        headless.run_script("n n").unwrap();
+        assert!(headless.screen().contains("(empty) (no description set)"));
+        headless.run_script("d m S e c o n d Enter").unwrap();
+        let screen = headless.screen();
+        assert!(screen.contains("Second"));
+        assert!(!screen.contains("(no description set)"));
+
+        fs::remove_dir_all(repo_path).ok();
+    }
+}
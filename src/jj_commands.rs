@@ -1,10 +1,11 @@
-use crate::ansi::strip_non_style_ansi;
+use crate::command_log::CommandLogEntry;
+use crate::executor::{ExecutorOutput, JjExecutor};
 use crate::model::GlobalArgs;
-use crate::terminal::{self, Term};
-use anyhow::{Result, anyhow};
+use crate::terminal::Term;
+use anyhow::Result;
 use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span};
-use std::{io::Read, process::Command};
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub struct JjCommand {
@@ -12,6 +13,9 @@ pub struct JjCommand {
     global_args: GlobalArgs,
     interactive_term: Option<Term>,
     return_output: ReturnOutput,
+    color: bool,
+    // Run by majjit for itself rather than by the user, like to load the log
+    internal: bool,
 }
 
 impl JjCommand {
@@ -26,80 +30,128 @@ impl JjCommand {
             global_args,
             interactive_term,
             return_output,
+            color: true,
+            internal: false,
         }
     }
 
+    fn _new_internal(args: &[&str], global_args: GlobalArgs, return_output: ReturnOutput) -> Self {
+        Self {
+            internal: true,
+            ..Self::_new(args, global_args, None, return_output)
+        }
+    }
+
+    fn _new_paged(args: &[&str], global_args: GlobalArgs, maybe_term: Option<Term>) -> Self {
+        match maybe_term {
+            None => Self::_new(args, global_args, None, ReturnOutput::Stdout),
+            Some(term) => Self::_new(args, global_args, Some(term), ReturnOutput::Stderr),
+        }
+    }
+
+    // For running the command away from majjit's executor, like on another thread
+    pub fn global_flags_and_args(&self) -> (Vec<String>, Vec<String>) {
+        (self.global_flags(), self.args.clone())
+    }
+
+    pub fn command_line(&self) -> String {
+        format!("jj {}", self.args.join(" ").replace('\n', "\\n"))
+    }
+
     pub fn to_lines(&self) -> Vec<Line<'static>> {
         let line = Line::from(vec![
             Span::styled("❯", Style::default().fg(Color::Yellow)),
             Span::raw(" jj "),
-            Span::raw(self.args.join(" ")),
+            // Multi-line args like descriptions would otherwise break the line
+            Span::raw(self.args.join(" ").replace('\n', "\\n")),
         ]);
         let blank_line = Line::raw("");
         vec![line, blank_line]
     }
 
     pub fn run(&mut self) -> Result<String, JjCommandError> {
-        let output = match &self.interactive_term {
+        let start = Instant::now();
+        let result = match &self.interactive_term {
             None => self.run_noninteractive(),
             Some(term) => self.run_interactive(term.clone()),
-        }?;
+        };
+        if let Err(JjCommandError::Other { err }) = &result {
+            self.record(None, start.elapsed(), String::new(), err.to_string());
+        }
+        let output = result?;
         match self.return_output {
             ReturnOutput::Stdout => Ok(output.stdout),
             ReturnOutput::Stderr => Ok(output.stderr),
         }
     }
 
-    fn run_noninteractive(&self) -> Result<JjCommandOutput, JjCommandError> {
-        let mut command = self.base_command();
-        command.args(self.args.clone());
-        let output = command.output().map_err(JjCommandError::new_other)?;
-
-        let stderr = String::from_utf8_lossy(&output.stderr).into();
-        if output.status.success() {
-            let stdout = String::from_utf8(output.stdout).map_err(JjCommandError::new_other)?;
-            Ok(JjCommandOutput { stdout, stderr })
-        } else {
-            Err(JjCommandError::new_failed(stderr))
-        }
+    // Read-only commands majjit runs for itself are only recorded if asked for, as opposed to
+    // the ones the user runs
+    fn should_record(&self) -> bool {
+        !self.internal || self.global_args.command_log.record_internal
     }
 
-    fn run_interactive(&mut self, term: Term) -> Result<JjCommandOutput, JjCommandError> {
-        let mut command = self.base_command();
-        command.args(self.args.clone());
-        command.stderr(std::process::Stdio::piped());
-
-        terminal::relinquish_terminal().map_err(JjCommandError::new_other)?;
-
-        let mut child = command.spawn().map_err(JjCommandError::new_other)?;
-        let status = child.wait().map_err(JjCommandError::new_other)?;
+    fn record(&self, exit_code: Option<i32>, duration: Duration, stdout: String, stderr: String) {
+        if !self.should_record() {
+            return;
+        }
+        self.global_args.command_log.record(CommandLogEntry {
+            args: self.args.clone(),
+            exit_code,
+            duration,
+            stdout,
+            stderr,
+        });
+    }
 
-        let mut stderr = String::new();
-        child
-            .stderr
-            .take()
-            .ok_or_else(|| JjCommandError::new_other(anyhow!("No stderr")))?
-            .read_to_string(&mut stderr)
+    fn run_noninteractive(&self) -> Result<JjCommandOutput, JjCommandError> {
+        let start = Instant::now();
+        let output = self
+            .global_args
+            .executor
+            .run(&self.global_flags(), &self.args)
             .map_err(JjCommandError::new_other)?;
-        stderr = strip_non_style_ansi(&stderr);
+        self.handle_executor_output(output, start.elapsed())
+    }
 
-        terminal::takeover_terminal(term).map_err(JjCommandError::new_other)?;
+    fn run_interactive(&mut self, term: Term) -> Result<JjCommandOutput, JjCommandError> {
+        let start = Instant::now();
+        let output = self
+            .global_args
+            .executor
+            .run_interactive(&self.global_flags(), &self.args, term)
+            .map_err(JjCommandError::new_other)?;
+        self.handle_executor_output(output, start.elapsed())
+    }
 
-        if status.success() {
+    fn handle_executor_output(
+        &self,
+        output: ExecutorOutput,
+        duration: Duration,
+    ) -> Result<JjCommandOutput, JjCommandError> {
+        // Avoid copying the output of internal commands when they aren't recorded
+        if self.should_record() {
+            self.record(
+                output.exit_code,
+                duration,
+                output.stdout.clone(),
+                output.stderr.clone(),
+            );
+        }
+        if output.exit_code == Some(0) {
             Ok(JjCommandOutput {
-                stdout: "".to_string(),
-                stderr,
+                stdout: output.stdout,
+                stderr: output.stderr,
             })
         } else {
-            Err(JjCommandError::new_failed(stderr))
+            Err(JjCommandError::new_failed(output.stderr))
         }
     }
 
-    fn base_command(&self) -> Command {
-        let mut command = Command::new("jj");
+    fn global_flags(&self) -> Vec<String> {
         let args = [
             "--color",
-            "always",
+            if self.color { "always" } else { "never" },
             "--config",
             r#"templates.log_node=
             coalesce(
@@ -123,41 +175,211 @@ impl JjCommand {
             "--repository",
             &self.global_args.repository,
         ];
-        command.args(args);
+        let mut global_flags: Vec<String> = args.iter().map(|a| a.to_string()).collect();
 
         if self.global_args.ignore_immutable {
-            command.arg("--ignore-immutable");
+            global_flags.push("--ignore-immutable".to_string());
         }
 
-        command
+        global_flags
     }
 
     pub fn log(revset: &str, global_args: GlobalArgs) -> Self {
         let args = ["log", "--revisions", revset];
-        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
     }
 
-    pub fn diff_summary(change_id: &str, global_args: GlobalArgs) -> Self {
-        let args = ["diff", "--revisions", change_id, "--summary"];
-        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    pub fn diff_summary(diff_revisions: &DiffRevisions, global_args: GlobalArgs) -> Self {
+        let mut args = diff_revisions.to_args();
+        args.push("--summary");
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
     }
 
-    pub fn diff_file(change_id: &str, file: &str, global_args: GlobalArgs) -> Self {
-        let args = ["diff", "--revisions", change_id, file];
-        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    // Always uses the git format, which majjit parses and renders itself
+    pub fn diff_file(diff_revisions: &DiffRevisions, file: &str, global_args: GlobalArgs) -> Self {
+        let diff_options_args = global_args.diff_options.content_args();
+        let mut args = diff_revisions.to_args();
+        args.push("--git");
+        args.extend(diff_options_args.iter().map(String::as_str));
+        args.push(file);
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
     }
 
-    pub fn show(
-        change_id: &str,
+    pub fn diff_file_formatted(
+        diff_revisions: &DiffRevisions,
+        file: &str,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let diff_options_args = global_args.diff_options.to_args();
+        let mut args = diff_revisions.to_args();
+        args.extend(diff_options_args.iter().map(String::as_str));
+        args.push(file);
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    // Without color, since the file's content is used as is
+    pub fn file_show(revision: &str, file: &str, global_args: GlobalArgs) -> Self {
+        let args = ["file", "show", "--revision", revision, file];
+        let mut cmd = Self::_new_internal(&args, global_args, ReturnOutput::Stdout);
+        cmd.color = false;
+        cmd
+    }
+
+    pub fn file_list(revision: &str, global_args: GlobalArgs) -> Self {
+        let args = ["file", "list", "--revision", revision];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    // Fields are tab separated so the change id and author can be pulled out for the gutter
+    pub fn file_annotate(revision: &str, file: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "file",
+            "annotate",
+            "--revision",
+            revision,
+            "--template",
+            r#"commit.change_id().short(8) ++ "\t" ++ commit.author().name() ++ "\t" ++ content"#,
+            file,
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    pub fn log_detailed(revision: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "--revisions",
+            revision,
+            "--no-graph",
+            "--template",
+            "builtin_log_detailed",
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    pub fn evolog(change_id: &str, global_args: GlobalArgs) -> Self {
+        let args = ["evolog", "--revisions", change_id];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    // One line per version: its change id and commit id, then the change id and commit id of each
+    // predecessor
+    pub fn evolog_predecessors(change_id: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "evolog",
+            "--revisions",
+            change_id,
+            "--no-graph",
+            "--template",
+            r#"commit.change_id() ++ " " ++ commit.commit_id() ++ predecessors.map(|p| " " ++ p.change_id() ++ ":" ++ p.commit_id()).join("") ++ "\n""#,
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    pub fn change_ids(revset: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "--revisions",
+            revset,
+            "--no-graph",
+            "--template",
+            r#"change_id ++ "\n""#,
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    // One line per change: change id, commit id and first line of the description
+    pub fn stack_changes(
+        revset: &str,
+        at_operation: Option<&str>,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mut args = vec![
+            "log",
+            "--revisions",
+            revset,
+            "--no-graph",
+            "--template",
+            r#"change_id ++ " " ++ commit_id ++ " " ++ description.first_line() ++ "\n""#,
+        ];
+        if let Some(operation) = at_operation {
+            args.extend(["--at-operation", operation]);
+        }
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    // Like any command that loads the working copy, this snapshots it first
+    pub fn snapshot(global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "--revisions",
+            "@",
+            "--no-graph",
+            "--template",
+            "commit_id",
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    pub fn local_bookmark_names(revision: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "--revisions",
+            revision,
+            "--no-graph",
+            "--template",
+            r#"local_bookmarks.map(|b| b.name() ++ "\n").join("")"#,
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    // Lists the remote bookmarks tracked by a local bookmark, as name@remote
+    pub fn tracked_remote_bookmarks(bookmark: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "bookmark",
+            "list",
+            "--tracked",
+            "--template",
+            r#"if(remote, name ++ "@" ++ remote ++ "\n")"#,
+            bookmark,
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    // Without a terminal to hand over to jj's pager, the output is returned instead
+    pub fn show(change_id: &str, global_args: GlobalArgs, maybe_term: Option<Term>) -> Self {
+        let diff_options_args = global_args.diff_options.to_args();
+        let mut args = vec!["show", change_id];
+        args.extend(diff_options_args.iter().map(String::as_str));
+        Self::_new_paged(&args, global_args, maybe_term)
+    }
+
+    pub fn show_file_diff(
+        diff_revisions: &DiffRevisions,
+        file: &str,
+        global_args: GlobalArgs,
+        maybe_term: Option<Term>,
+    ) -> Self {
+        let diff_options_args = global_args.diff_options.to_args();
+        let mut args = diff_revisions.to_args();
+        args.extend(diff_options_args.iter().map(String::as_str));
+        args.push(file);
+        Self::_new_paged(&args, global_args, maybe_term)
+    }
+
+    pub fn show_diff_from(
+        from: &str,
+        to: &str,
         maybe_file_path: Option<&str>,
         global_args: GlobalArgs,
-        term: Term,
+        maybe_term: Option<Term>,
     ) -> Self {
-        let args = match maybe_file_path {
-            None => vec!["show", change_id],
-            Some(file_path) => vec!["diff", "--revisions", change_id, file_path],
-        };
-        Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
+        let diff_options_args = global_args.diff_options.to_args();
+        let mut args = vec!["diff", "--from", from, "--to", to];
+        args.extend(diff_options_args.iter().map(String::as_str));
+        if let Some(file_path) = maybe_file_path {
+            args.push(file_path);
+        }
+        Self::_new_paged(&args, global_args, maybe_term)
     }
 
     pub fn describe(change_id: &str, global_args: GlobalArgs, term: Term) -> Self {
@@ -165,6 +387,23 @@ impl JjCommand {
         Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
+    pub fn description(change_id: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "--revisions",
+            change_id,
+            "--no-graph",
+            "--template",
+            "description",
+        ];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stdout)
+    }
+
+    pub fn describe_message(change_id: &str, message: &str, global_args: GlobalArgs) -> Self {
+        let args = ["describe", change_id, "--message", message];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn new(change_id: &str, global_args: GlobalArgs) -> Self {
         let args = ["new", change_id];
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
@@ -175,6 +414,57 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    // Without a placement, the duplicate gets the same parents as the original
+    pub fn duplicate(
+        change_id: &str,
+        placement: Option<(Placement, &str)>,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mut args = vec!["duplicate", change_id];
+        if let Some((placement, revision)) = placement {
+            args.extend([placement.flag(), revision]);
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn revert(
+        change_id: &str,
+        placement: Placement,
+        revision: &str,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let args = [
+            "revert",
+            "--revisions",
+            change_id,
+            placement.flag(),
+            revision,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn parallelize(revset: &str, global_args: GlobalArgs) -> Self {
+        let args = ["parallelize", revset];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    // The offset is left for jj to validate, since it comes straight from a prompt
+    pub fn next(offset: &str, edit: bool, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["next", offset];
+        if edit {
+            args.push("--edit");
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn prev(offset: &str, edit: bool, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["prev", offset];
+        if edit {
+            args.push("--edit");
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn abandon(change_id: &str, global_args: GlobalArgs) -> Self {
         let args = ["abandon", change_id];
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
@@ -190,6 +480,11 @@ impl JjCommand {
         Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
+    pub fn commit_message(message: &str, global_args: GlobalArgs) -> Self {
+        let args = ["commit", "--message", message];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn restore(
         change_id: &str,
         maybe_file_path: Option<&str>,
@@ -202,6 +497,27 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    pub fn restore_from(
+        from: &str,
+        to: &str,
+        maybe_file_path: Option<&str>,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mut args = vec!["restore", "--from", from, "--to", to];
+        if let Some(file_path) = maybe_file_path {
+            args.push(file_path);
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn absorb(change_id: &str, maybe_file_path: Option<&str>, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["absorb", "--from", change_id];
+        if let Some(file_path) = maybe_file_path {
+            args.push(file_path);
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn squash_noninteractive(
         change_id: &str,
         maybe_file_path: Option<&str>,
@@ -242,34 +558,131 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    pub fn push_dry_run(global_args: GlobalArgs) -> Self {
+        let args = ["git", "push", "--dry-run"];
+        Self::_new_internal(&args, global_args, ReturnOutput::Stderr)
+    }
+
     pub fn bookmark_set_master(change_id: &str, global_args: GlobalArgs) -> Self {
         let args = ["bookmark", "set", "master", "--revision", change_id];
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
-    pub fn ensure_valid_repo(repository: &str) -> Result<String, JjCommandError> {
-        let args = [
-            "--repository",
-            repository,
-            "workspace",
-            "root",
-            "--color",
-            "always",
-        ];
-        let output = Command::new("jj")
-            .args(args)
-            .output()
+    // Runs before there are global args to build a command with, so it uses the executor directly
+    pub fn ensure_valid_repo(
+        repository: &str,
+        executor: &dyn JjExecutor,
+    ) -> Result<String, JjCommandError> {
+        let global_flags = ["--color", "always", "--repository", repository].map(str::to_string);
+        let args = ["workspace", "root"].map(str::to_string);
+        let output = executor
+            .run(&global_flags, &args)
             .map_err(JjCommandError::new_other)?;
 
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout)
-                .to_string()
-                .trim()
-                .to_string())
+        if output.exit_code == Some(0) {
+            Ok(output.stdout.trim().to_string())
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr).into();
-            Err(JjCommandError::new_failed(stderr))
+            Err(JjCommandError::new_failed(output.stderr))
+        }
+    }
+}
+
+// Where rewrite commands like duplicate and revert put the new commits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Onto,
+    After,
+    Before,
+}
+
+impl Placement {
+    fn flag(self) -> &'static str {
+        match self {
+            Self::Onto => "--destination",
+            Self::After => "--insert-after",
+            Self::Before => "--insert-before",
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Onto => "onto",
+            Self::After => "after",
+            Self::Before => "before",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DiffRevisions {
+    Change(String),
+    Interdiff { from: String, to: String },
+    FromTo { from: String, to: String },
+}
+
+impl DiffRevisions {
+    // The revision on the new side of the diff
+    pub fn to_revision(&self) -> &str {
+        match self {
+            Self::Change(change_id) => change_id,
+            Self::Interdiff { to, .. } | Self::FromTo { to, .. } => to,
+        }
+    }
+
+    fn to_args(&self) -> Vec<&str> {
+        match self {
+            Self::Change(change_id) => vec!["diff", "--revisions", change_id],
+            Self::Interdiff { from, to } => vec!["interdiff", "--from", from, "--to", to],
+            Self::FromTo { from, to } => vec!["diff", "--from", from, "--to", to],
+        }
+    }
+}
+
+// Passed to jj when it renders diffs itself, and mimicked by majjit when rendering hunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+    #[default]
+    ColorWords,
+    Git,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    // None leaves the format up to the user's ui.diff-formatter
+    pub format: Option<DiffFormat>,
+    // None leaves the number of context lines up to jj
+    pub context: Option<usize>,
+    pub ignore_all_space: bool,
+    pub ignore_space_change: bool,
+}
+
+impl DiffOptions {
+    pub const DEFAULT_CONTEXT: usize = 3;
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        match self.format {
+            Some(DiffFormat::ColorWords) => args.push("--color-words".to_string()),
+            Some(DiffFormat::Git) => args.push("--git".to_string()),
+            None => {}
+        }
+        args.extend(self.content_args());
+        args
+    }
+
+    // Args which affect what's in the diff rather than how it's formatted
+    pub fn content_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(context) = self.context {
+            args.push(format!("--context={context}"));
         }
+        if self.ignore_all_space {
+            args.push("--ignore-all-space".to_string());
+        }
+        if self.ignore_space_change {
+            args.push("--ignore-space-change".to_string());
+        }
+        args
     }
 }
 
@@ -292,7 +705,7 @@ impl JjCommandError {
         }
     }
 
-    fn new_other(err: impl Into<anyhow::Error>) -> Self {
+    pub fn new_other(err: impl Into<anyhow::Error>) -> Self {
         Self::Other { err: err.into() }
     }
 }
@@ -315,3 +728,139 @@ pub struct JjCommandOutput {
     pub stdout: String,
     pub stderr: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{command_log::CommandLog, fixture_executor::FixtureExecutor};
+    use std::rc::Rc;
+
+    fn global_args() -> GlobalArgs {
+        GlobalArgs::new(
+            "/repo".to_string(),
+            Rc::new(FixtureExecutor::default()),
+            CommandLog::new(false),
+        )
+    }
+
+    #[test]
+    fn global_flags_follow_global_args() {
+        let mut global_args = global_args();
+        let cmd = JjCommand::log("@", global_args.clone());
+        let global_flags = cmd.global_flags();
+        assert_eq!(global_flags[..2], ["--color", "always"]);
+        assert!(global_flags.ends_with(&["--repository".to_string(), "/repo".to_string()]));
+
+        let cmd = JjCommand::file_show("@", "hello.txt", global_args.clone());
+        assert_eq!(cmd.global_flags()[..2], ["--color", "never"]);
+
+        global_args.ignore_immutable = true;
+        let cmd = JjCommand::log("@", global_args);
+        assert_eq!(cmd.global_flags().last().unwrap(), "--ignore-immutable");
+    }
+
+    #[test]
+    fn ensure_valid_repo_runs_through_the_executor() {
+        let executor = FixtureExecutor::from_fixtures(concat!(
+            ">>> jj\nworkspace\nroot\n>>> exit 0\n>>> stdout\n/home/me/repo\n",
+            ">>> stderr\n>>> end\n"
+        ))
+        .unwrap();
+        assert_eq!(
+            JjCommand::ensure_valid_repo("repo", &executor).unwrap(),
+            "/home/me/repo"
+        );
+        assert_eq!(
+            executor.calls(),
+            [[
+                "--color",
+                "always",
+                "--repository",
+                "repo",
+                "workspace",
+                "root"
+            ]]
+        );
+
+        let executor = FixtureExecutor::from_fixtures(concat!(
+            ">>> jj\nworkspace\nroot\n>>> exit 1\n>>> stdout\n",
+            ">>> stderr\nError: There is no jj repo in \"repo\"\n>>> end\n"
+        ))
+        .unwrap();
+        let err = JjCommand::ensure_valid_repo("repo", &executor).unwrap_err();
+        assert_eq!(err.to_string(), "Error: There is no jj repo in \"repo\"");
+    }
+
+    #[test]
+    fn diff_file_uses_git_format_and_content_options() {
+        let mut global_args = global_args();
+        global_args.diff_options = DiffOptions {
+            format: Some(DiffFormat::ColorWords),
+            context: Some(5),
+            ignore_all_space: true,
+            ignore_space_change: false,
+        };
+        let diff_revisions = DiffRevisions::Interdiff {
+            from: "abc".to_string(),
+            to: "def".to_string(),
+        };
+
+        let cmd = JjCommand::diff_file(&diff_revisions, "src/main.rs", global_args);
+        assert_eq!(
+            cmd.args,
+            [
+                "interdiff",
+                "--from",
+                "abc",
+                "--to",
+                "def",
+                "--git",
+                "--context=5",
+                "--ignore-all-space",
+                "src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn rewrite_commands_pass_placement_flags() {
+        let cmd = JjCommand::duplicate("abc", None, global_args());
+        assert_eq!(cmd.args, ["duplicate", "abc"]);
+        let cmd = JjCommand::duplicate("abc", Some((Placement::After, "@")), global_args());
+        assert_eq!(cmd.args, ["duplicate", "abc", "--insert-after", "@"]);
+        let cmd = JjCommand::revert("abc", Placement::Onto, "@", global_args());
+        assert_eq!(
+            cmd.args,
+            ["revert", "--revisions", "abc", "--destination", "@"]
+        );
+    }
+
+    #[test]
+    fn show_returns_stdout_only_without_a_terminal() {
+        let cmd = JjCommand::show("abc", global_args(), None);
+        assert!(cmd.interactive_term.is_none());
+        assert!(matches!(cmd.return_output, ReturnOutput::Stdout));
+        assert_eq!(cmd.args, ["show", "abc"]);
+    }
+
+    #[test]
+    fn only_commands_the_user_runs_are_recorded_by_default() {
+        assert!(!JjCommand::log("@", global_args()).should_record());
+        assert!(!JjCommand::push_dry_run(global_args()).should_record());
+        assert!(JjCommand::show("abc", global_args(), None).should_record());
+        assert!(JjCommand::new("abc", global_args()).should_record());
+
+        let mut global_args = global_args();
+        global_args.command_log = CommandLog::new(true);
+        assert!(JjCommand::log("@", global_args).should_record());
+    }
+
+    #[test]
+    fn multi_line_args_stay_on_one_line() {
+        let cmd = JjCommand::describe_message("abc", "Summary\n\nBody", global_args());
+        assert_eq!(
+            cmd.command_line(),
+            "jj describe abc --message Summary\\n\\nBody"
+        );
+    }
+}
@@ -1,29 +1,156 @@
 use crate::model::GlobalArgs;
-use crate::{ansi::strip_ansi, jj_commands::JjCommand};
+use crate::{
+    ansi::strip_ansi,
+    jj_commands::{DiffFormat, DiffRevisions, JjCommand},
+};
 use ansi_to_tui::IntoText;
-use anyhow::{Error, Result, anyhow, bail};
+use anyhow::{Error, Result, anyhow};
 use ratatui::{
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
 };
 use regex::Regex;
-use std::fmt;
+use std::{any::Any, collections::HashMap, fmt, mem};
+
+// Fold and selection state of the log, keyed by change id so it survives reloading the log
+#[derive(Debug)]
+pub struct ViewState {
+    // Unfolded commits, with the paths of their unfolded file diffs
+    unfolded_commits: Vec<(String, Vec<String>)>,
+    // Change id of the selected node's commit, if it's under one
+    selected_change_id: Option<String>,
+    selected_tree_pos: TreePosition,
+}
 
 #[derive(Debug)]
 pub struct JjLog {
     pub log_tree: Vec<CommitOrText>,
+    // Revisions compared from and to, shown above the log while set
+    comparison: Option<(String, String)>,
+    // Old operation and revset of a range-diff, shown above the log while set
+    range_diff: Option<(String, String)>,
 }
 
 impl JjLog {
     pub fn new() -> Result<Self> {
         Ok(JjLog {
             log_tree: Vec::new(),
+            comparison: None,
+            range_diff: None,
         })
     }
 
-    pub fn load_log_tree(&mut self, global_args: &GlobalArgs, revset: &str) -> Result<()> {
+    // Returns why any comparisons were closed
+    pub fn load_log_tree(&mut self, global_args: &GlobalArgs, revset: &str) -> Result<Vec<String>> {
         self.log_tree = CommitOrText::load_all(global_args, revset)?;
-        Ok(())
+        let mut closed = Vec::new();
+        // The revisions may no longer resolve after the repo changes, so drop the comparisons then
+        if let Some((old_operation, revset)) = &self.range_diff {
+            match RangeDiff::load(global_args, old_operation.clone(), revset.clone()) {
+                Ok(range_diff) => self.log_tree.insert(0, CommitOrText::RangeDiff(range_diff)),
+                Err(err) => {
+                    closed.push(format!(
+                        "Closed the range-diff of {revset} at operation {old_operation}: {}",
+                        strip_ansi(&err.to_string())
+                    ));
+                    self.range_diff = None;
+                }
+            }
+        }
+        if let Some((from, to)) = &self.comparison {
+            match Comparison::load(global_args, from.clone(), to.clone()) {
+                Ok(comparison) => self
+                    .log_tree
+                    .insert(0, CommitOrText::Comparison(comparison)),
+                Err(err) => {
+                    closed.push(format!(
+                        "Closed the comparison of {from} → {to}: {}",
+                        strip_ansi(&err.to_string())
+                    ));
+                    self.comparison = None;
+                }
+            }
+        }
+        Ok(closed)
+    }
+
+    pub fn view_state(&self, selected_tree_pos: &TreePosition) -> ViewState {
+        let unfolded_commits = self
+            .log_tree
+            .iter()
+            .filter_map(|item| match item {
+                CommitOrText::Commit(commit) if commit.unfolded => Some(commit),
+                _ => None,
+            })
+            .map(|commit| {
+                let unfolded_paths = commit
+                    .file_diffs
+                    .iter()
+                    .filter(|file_diff| file_diff.unfolded)
+                    .map(|file_diff| file_diff.path.clone())
+                    .collect();
+                (commit.change_id.clone(), unfolded_paths)
+            })
+            .collect();
+        ViewState {
+            unfolded_commits,
+            selected_change_id: self
+                .get_tree_commit(selected_tree_pos)
+                .map(|commit| commit.change_id.clone()),
+            selected_tree_pos: selected_tree_pos.clone(),
+        }
+    }
+
+    // Returns where the selected node would now be. It may no longer exist, or be empty if its
+    // commit is gone.
+    pub fn restore_view_state(
+        &mut self,
+        global_args: &GlobalArgs,
+        view_state: &ViewState,
+    ) -> Result<TreePosition> {
+        for commit_or_text in self.log_tree.iter_mut() {
+            let CommitOrText::Commit(commit) = commit_or_text else {
+                continue;
+            };
+            let Some((_, unfolded_paths)) = view_state
+                .unfolded_commits
+                .iter()
+                .find(|(change_id, _)| *change_id == commit.change_id)
+            else {
+                continue;
+            };
+
+            commit.toggle_fold(global_args)?;
+            for file_diff in commit.file_diffs.iter_mut() {
+                if unfolded_paths.contains(&file_diff.path) {
+                    file_diff.toggle_fold(global_args)?;
+                }
+            }
+        }
+
+        let mut tree_pos = view_state.selected_tree_pos.clone();
+        if let Some(selected_change_id) = &view_state.selected_change_id {
+            let commit_idx = self.log_tree.iter().position(|item| {
+                matches!(item, CommitOrText::Commit(commit) if commit.change_id == *selected_change_id)
+            });
+            match commit_idx {
+                Some(commit_idx) => tree_pos[COMMIT_OR_TEXT_IDX] = commit_idx,
+                None => tree_pos.clear(),
+            }
+        }
+        Ok(tree_pos)
+    }
+
+    pub fn set_comparison(&mut self, comparison: Option<(String, String)>) {
+        self.comparison = comparison;
+    }
+
+    pub fn set_range_diff(&mut self, range_diff: Option<(String, String)>) {
+        self.range_diff = range_diff;
+    }
+
+    pub fn has_comparisons(&self) -> bool {
+        self.comparison.is_some() || self.range_diff.is_some()
     }
 
     pub fn flatten_log(&mut self) -> Result<(Vec<Text<'static>>, Vec<TreePosition>)> {
@@ -42,62 +169,212 @@ impl JjLog {
     }
 
     pub fn get_tree_node(&mut self, tree_pos: &TreePosition) -> Result<&mut dyn LogTreeNode> {
-        // Traverse to commit
-        let commit_or_text = &mut self.log_tree[tree_pos[COMMIT_OR_TEXT_IDX]];
-        let commit = match commit_or_text {
-            CommitOrText::InfoText(info_text) => {
-                return Ok(info_text);
-            }
+        let mut node: &mut dyn LogTreeNode = match &mut self.log_tree[tree_pos[COMMIT_OR_TEXT_IDX]]
+        {
             CommitOrText::Commit(commit) => commit,
+            CommitOrText::InfoText(info_text) => info_text,
+            CommitOrText::Comparison(comparison) => comparison,
+            CommitOrText::RangeDiff(range_diff) => range_diff,
         };
 
-        let file_diff_idx = if tree_pos.len() <= FILE_DIFF_IDX {
-            return Ok(commit);
-        } else {
-            tree_pos[FILE_DIFF_IDX]
-        };
-
-        // Traverse to file diff
-        if !commit.loaded {
-            bail!("Trying to get unloaded file diffs for commit");
+        for child_idx in &tree_pos[COMMIT_OR_TEXT_IDX + 1..] {
+            node = node
+                .children_mut()
+                .into_iter()
+                .nth(*child_idx)
+                .ok_or_else(|| anyhow!("Trying to get unloaded tree node: {tree_pos:?}"))?;
         }
-        let file_diff = &mut commit.file_diffs[file_diff_idx];
-        let diff_hunk_idx = if tree_pos.len() <= DIFF_HUNK_IDX {
-            return Ok(file_diff);
-        } else {
-            tree_pos[DIFF_HUNK_IDX]
+
+        Ok(node)
+    }
+
+    // Returns every node from the top level down to the node at the given position
+    fn get_tree_path(&self, tree_pos: &TreePosition) -> Vec<&dyn LogTreeNode> {
+        let mut node: &dyn LogTreeNode = match &self.log_tree[tree_pos[COMMIT_OR_TEXT_IDX]] {
+            CommitOrText::Commit(commit) => commit,
+            CommitOrText::InfoText(info_text) => info_text,
+            CommitOrText::Comparison(comparison) => comparison,
+            CommitOrText::RangeDiff(range_diff) => range_diff,
         };
+        let mut path = vec![node];
 
-        // Traverse to diff hunk
-        if !file_diff.loaded {
-            bail!("Trying to get unloaded diff hunks for file diff");
+        for child_idx in &tree_pos[COMMIT_OR_TEXT_IDX + 1..] {
+            match node.children().into_iter().nth(*child_idx) {
+                None => break,
+                Some(child) => {
+                    node = child;
+                    path.push(node);
+                }
+            }
         }
-        let diff_hunk = &mut file_diff.diff_hunks[diff_hunk_idx];
-        let diff_hunk_line_idx = if tree_pos.len() <= DIFF_HUNK_LINE_IDX {
-            return Ok(diff_hunk);
-        } else {
-            tree_pos[DIFF_HUNK_LINE_IDX]
-        };
 
-        // Traverse to diff hunk line
-        let diff_hunk_line = &mut diff_hunk.diff_hunk_lines[diff_hunk_line_idx];
-        Ok(diff_hunk_line)
+        path
+    }
+
+    // Finds the deepest node of the given type on the path to the given position
+    fn find_in_tree_path<T: 'static>(&self, tree_pos: &TreePosition) -> Option<&T> {
+        self.get_tree_path(tree_pos)
+            .into_iter()
+            .rev()
+            .find_map(|node| node.as_any().downcast_ref::<T>())
     }
 
     pub fn get_tree_commit(&self, tree_pos: &TreePosition) -> Option<&Commit> {
         let commit_or_text = &self.log_tree[tree_pos[COMMIT_OR_TEXT_IDX]];
         match commit_or_text {
-            CommitOrText::InfoText(_) => None,
+            CommitOrText::InfoText(_)
+            | CommitOrText::Comparison(_)
+            | CommitOrText::RangeDiff(_) => None,
             CommitOrText::Commit(commit) => Some(commit),
         }
     }
 
     pub fn get_tree_file_diff(&self, tree_pos: &TreePosition) -> Option<&FileDiff> {
-        if tree_pos.len() <= FILE_DIFF_IDX {
-            return None;
+        self.find_in_tree_path(tree_pos)
+    }
+
+    pub fn get_tree_diff_hunk(&self, tree_pos: &TreePosition) -> Option<&DiffHunk> {
+        self.find_in_tree_path(tree_pos)
+    }
+
+    pub fn get_tree_evolog_entry(&self, tree_pos: &TreePosition) -> Option<&EvologEntry> {
+        self.find_in_tree_path(tree_pos)
+    }
+
+    pub fn get_tree_file_tree_file(&self, tree_pos: &TreePosition) -> Option<&FileTreeFile> {
+        self.find_in_tree_path(tree_pos)
+    }
+
+    // The diff lines shown at a node, two for a side-by-side row that pairs a removed line with an
+    // added one
+    fn get_tree_diff_lines(&self, tree_pos: &TreePosition) -> Vec<&DiffHunkLine> {
+        let path = self.get_tree_path(tree_pos);
+        let Some(node) = path.last() else {
+            return Vec::new();
+        };
+        if let Some(line) = node.as_any().downcast_ref::<DiffHunkLine>() {
+            return vec![line];
+        }
+        match node.as_any().downcast_ref::<SideBySideRow>() {
+            None => Vec::new(),
+            Some(row) => row.diff_hunk_lines(),
+        }
+    }
+
+    // A patch of the diff lines at the given nodes. Side-by-side rows are unpaired again, so
+    // removed lines come before the added lines they're shown next to.
+    pub fn get_tree_diff_lines_patch(&self, tree_positions: &[TreePosition]) -> String {
+        let mut patch = String::new();
+        let mut added = String::new();
+        let mut last_kind = DiffLineKind::Context;
+        for line in tree_positions
+            .iter()
+            .flat_map(|tree_pos| self.get_tree_diff_lines(tree_pos))
+        {
+            let Some(patch_line) = line.patch_line() else {
+                continue;
+            };
+            // A no newline marker belongs to the line before it
+            let kind = match line.kind {
+                DiffLineKind::NoNewline => last_kind,
+                kind => kind,
+            };
+            if kind == DiffLineKind::Added {
+                added.push_str(&patch_line);
+                added.push('\n');
+            } else {
+                if kind != DiffLineKind::Removed {
+                    patch.push_str(&added);
+                    added.clear();
+                }
+                patch.push_str(&patch_line);
+                patch.push('\n');
+            }
+            last_kind = kind;
+        }
+        patch.push_str(&added);
+        patch
+    }
+
+    // Where the diff line is in the new version of the file, None for removed lines and other nodes
+    pub fn get_tree_diff_line_new_line_num(&self, tree_pos: &TreePosition) -> Option<u32> {
+        self.get_tree_diff_lines(tree_pos)
+            .iter()
+            .find_map(|line| line.new_line_num)
+    }
+
+    fn is_file_tree_file(&self, tree_pos: &TreePosition) -> bool {
+        self.get_tree_path(tree_pos)
+            .last()
+            .is_some_and(|node| node.as_any().is::<FileTreeFile>())
+    }
+
+    pub fn is_diff_hunk_line(&self, tree_pos: &TreePosition) -> bool {
+        self.get_tree_path(tree_pos).last().is_some_and(|node| {
+            node.as_any().is::<DiffHunkLine>() || node.as_any().is::<SideBySideRow>()
+        })
+    }
+
+    pub fn reload_diffs(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        for commit_or_text in self.log_tree.iter_mut() {
+            match commit_or_text {
+                CommitOrText::Commit(commit) => commit.reload_diffs(global_args)?,
+                CommitOrText::Comparison(comparison) => comparison.reload_diffs(global_args)?,
+                CommitOrText::RangeDiff(range_diff) => range_diff.reload_diffs(global_args)?,
+                CommitOrText::InfoText(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn resize(&mut self, width: u16) {
+        for commit_or_text in self.log_tree.iter_mut() {
+            match commit_or_text {
+                CommitOrText::Commit(commit) => commit.resize(width),
+                CommitOrText::Comparison(comparison) => comparison.resize(width),
+                CommitOrText::RangeDiff(range_diff) => range_diff.resize(width),
+                CommitOrText::InfoText(_) => {}
+            }
+        }
+    }
+
+    // Returns where the selected node is after switching, since diff lines and side-by-side rows
+    // don't line up
+    pub fn toggle_side_by_side(
+        &mut self,
+        tree_pos: &TreePosition,
+        width: u16,
+    ) -> Result<TreePosition> {
+        let Some(file_diff_depth) = self
+            .get_tree_path(tree_pos)
+            .iter()
+            .rposition(|node| node.as_any().is::<FileDiff>())
+        else {
+            return Ok(tree_pos.clone());
+        };
+        let hunk_depth = file_diff_depth + 1;
+        let line_depth = hunk_depth + 1;
+        let hunk_pos = tree_pos[..tree_pos.len().min(line_depth)].to_vec();
+        let maybe_line_idx = tree_pos.get(line_depth).and_then(|child_idx| {
+            self.get_tree_diff_hunk(&hunk_pos)?
+                .child_line_idx(*child_idx)
+        });
+
+        let file_diff_pos = tree_pos[..=file_diff_depth].to_vec();
+        let node = self.get_tree_node(&file_diff_pos)?;
+        if let Some(file_diff) = node.as_any_mut().downcast_mut::<FileDiff>() {
+            match file_diff.side_by_side_width {
+                None => file_diff.set_side_by_side(Some(width)),
+                Some(_) => file_diff.set_side_by_side(None),
+            }
+        }
+
+        let maybe_child_idx = maybe_line_idx
+            .and_then(|line_idx| self.get_tree_diff_hunk(&hunk_pos)?.line_child_idx(line_idx));
+        match maybe_child_idx {
+            Some(child_idx) => Ok([hunk_pos, vec![child_idx]].concat()),
+            None => Ok(hunk_pos),
         }
-        let commit = self.get_tree_commit(tree_pos)?;
-        Some(&commit.file_diffs[tree_pos[FILE_DIFF_IDX]])
     }
 
     pub fn get_current_commit(&self) -> Option<&Commit> {
@@ -108,13 +385,30 @@ impl JjLog {
         })
     }
 
+    // Change ids may be abbreviated to different lengths, so match on either being a prefix
+    pub fn find_commit(&self, change_id: &str) -> Option<&Commit> {
+        self.log_tree.iter().find_map(|item| match item {
+            CommitOrText::Commit(commit)
+                if commit.change_id.starts_with(change_id)
+                    || change_id.starts_with(&commit.change_id) =>
+            {
+                Some(commit)
+            }
+            _ => None,
+        })
+    }
+
     pub fn toggle_fold(
         &mut self,
         global_args: &GlobalArgs,
         tree_pos: &TreePosition,
     ) -> Result<usize> {
-        let mut tree_pos = tree_pos.clone();
-        tree_pos.truncate(DIFF_HUNK_IDX + 1);
+        // Diff hunk lines and files in the file tree can't be folded, so fold their parent instead
+        let tree_pos = if self.is_diff_hunk_line(tree_pos) || self.is_file_tree_file(tree_pos) {
+            get_parent_tree_position(tree_pos).unwrap()
+        } else {
+            tree_pos.clone()
+        };
         let node = self.get_tree_node(&tree_pos)?;
         node.toggle_fold(global_args)?;
         Ok(node.flat_log_idx())
@@ -131,14 +425,27 @@ pub trait LogTreeNode {
     ) -> Result<()>;
     fn flat_log_idx(&self) -> usize;
     fn children(&self) -> Vec<&dyn LogTreeNode>;
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode>;
     fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn reload_diffs(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        for child in self.children_mut() {
+            child.reload_diffs(global_args)?;
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u16) {
+        for child in self.children_mut() {
+            child.resize(width);
+        }
+    }
 }
 
 pub type TreePosition = Vec<usize>;
 pub const COMMIT_OR_TEXT_IDX: usize = 0;
-pub const FILE_DIFF_IDX: usize = 1;
-pub const DIFF_HUNK_IDX: usize = 2;
-pub const DIFF_HUNK_LINE_IDX: usize = 3;
 
 pub fn get_parent_tree_position(tree_pos: &TreePosition) -> Option<TreePosition> {
     let mut tree_pos = tree_pos.clone();
@@ -155,6 +462,8 @@ pub fn get_parent_tree_position(tree_pos: &TreePosition) -> Option<TreePosition>
 pub enum CommitOrText {
     Commit(Commit),
     InfoText(InfoText),
+    Comparison(Comparison),
+    RangeDiff(RangeDiff),
 }
 
 impl CommitOrText {
@@ -195,6 +504,12 @@ impl CommitOrText {
             CommitOrText::InfoText(info_text) => {
                 info_text.flatten(tree_pos, log_list, log_list_tree_positions)
             }
+            CommitOrText::Comparison(comparison) => {
+                comparison.flatten(tree_pos, log_list, log_list_tree_positions)
+            }
+            CommitOrText::RangeDiff(range_diff) => {
+                range_diff.flatten(tree_pos, log_list, log_list_tree_positions)
+            }
         }
     }
 
@@ -202,6 +517,8 @@ impl CommitOrText {
         match self {
             CommitOrText::Commit(commit) => commit.flat_log_idx(),
             CommitOrText::InfoText(info_text) => info_text.flat_log_idx,
+            CommitOrText::Comparison(comparison) => comparison.flat_log_idx,
+            CommitOrText::RangeDiff(range_diff) => range_diff.flat_log_idx,
         }
     }
 }
@@ -209,7 +526,7 @@ impl CommitOrText {
 #[derive(Debug)]
 pub struct Commit {
     pub change_id: String,
-    _commit_id: String,
+    pub commit_id: String,
     pub current_working_copy: bool,
     has_conflict: bool,
     _empty: bool,
@@ -224,6 +541,8 @@ pub struct Commit {
     unfolded: bool,
     loaded: bool,
     file_diffs: Vec<FileDiff>,
+    evolog: Evolog,
+    file_tree: FileTree,
     pub flat_log_idx: usize,
 }
 
@@ -253,7 +572,7 @@ impl Commit {
             .ok_or_else(|| anyhow!("Cannot parse line 1 graph chars part 2"))?
             .as_str()
             .into();
-        let change_id = captures
+        let change_id: String = captures
             .get(4)
             .ok_or_else(|| anyhow!("Cannot parse commit change id"))?
             .as_str()
@@ -312,9 +631,12 @@ impl Commit {
             .as_str()
             .into();
 
+        let evolog = Evolog::new(change_id.clone(), graph_indent.clone());
+        let file_tree = FileTree::new(change_id.clone(), graph_indent.clone());
+
         Ok(Commit {
             change_id,
-            _commit_id: commit_id,
+            commit_id,
             current_working_copy,
             has_conflict,
             _empty: empty,
@@ -329,6 +651,8 @@ impl Commit {
             unfolded: false,
             loaded: false,
             file_diffs: Vec::new(),
+            evolog,
+            file_tree,
             flat_log_idx: 0,
         })
     }
@@ -388,6 +712,16 @@ impl LogTreeNode for Commit {
             file_diff.flatten(new_pos, log_list, log_list_tree_positions)?;
         }
 
+        let mut new_pos = tree_pos.clone();
+        new_pos.push(self.file_diffs.len());
+        self.evolog
+            .flatten(new_pos, log_list, log_list_tree_positions)?;
+
+        let mut new_pos = tree_pos.clone();
+        new_pos.push(self.file_diffs.len() + 1);
+        self.file_tree
+            .flatten(new_pos, log_list, log_list_tree_positions)?;
+
         Ok(())
     }
 
@@ -396,10 +730,25 @@ impl LogTreeNode for Commit {
     }
 
     fn children(&self) -> Vec<&dyn LogTreeNode> {
-        self.file_diffs
+        let mut children: Vec<&dyn LogTreeNode> = self
+            .file_diffs
             .iter()
             .map(|fd| fd as &dyn LogTreeNode)
-            .collect()
+            .collect();
+        children.push(&self.evolog);
+        children.push(&self.file_tree);
+        children
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        let mut children: Vec<&mut dyn LogTreeNode> = self
+            .file_diffs
+            .iter_mut()
+            .map(|fd| fd as &mut dyn LogTreeNode)
+            .collect();
+        children.push(&mut self.evolog);
+        children.push(&mut self.file_tree);
+        children
     }
 
     fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
@@ -409,13 +758,22 @@ impl LogTreeNode for Commit {
         }
 
         if !self.loaded {
-            let file_diffs = FileDiff::load_all(global_args, &self.change_id, &self.graph_indent)?;
+            let diff_revisions = DiffRevisions::Change(self.change_id.clone());
+            let file_diffs = FileDiff::load_all(global_args, &diff_revisions, &self.graph_indent)?;
             self.file_diffs = file_diffs;
             self.loaded = true;
         }
 
         Ok(())
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -458,106 +816,67 @@ impl LogTreeNode for InfoText {
         Vec::new()
     }
 
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        Vec::new()
+    }
+
     fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
         Ok(())
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
+// Diff between two arbitrary revisions, see DiffRevisions::FromTo
 #[derive(Debug)]
-pub struct FileDiff {
-    change_id: String,
-    pub path: String,
-    description: String,
-    status: FileDiffStatus,
-    graph_indent: String,
+pub struct Comparison {
+    from: String,
+    to: String,
     unfolded: bool,
-    loaded: bool,
-    diff_hunks: Vec<DiffHunk>,
+    file_diffs: Vec<FileDiff>,
     flat_log_idx: usize,
 }
 
-impl FileDiff {
-    pub fn new(change_id: String, pretty_string: String, graph_indent: String) -> Result<Self> {
-        let clean_string = strip_ansi(&pretty_string);
-        let re = Regex::new(r"^([MADRC])\s+(.+)$").unwrap();
-
-        let captures = re
-            .captures(&clean_string)
-            .ok_or_else(|| anyhow!("Cannot parse file diff string: {clean_string}"))?;
-        let status = captures
-            .get(1)
-            .ok_or_else(|| anyhow!("Cannot parse file diff status"))?
-            .as_str()
-            .parse::<FileDiffStatus>()?;
-        let description: String = captures
-            .get(2)
-            .ok_or_else(|| anyhow!("Cannot parse file diff path"))?
-            .as_str()
-            .into();
-
-        let path = match status {
-            FileDiffStatus::Renamed | FileDiffStatus::Copied => {
-                let rename_regex = Regex::new(r"^(.+)\{(.+?)\s*=>\s*(.+?)\}$").unwrap();
-                let captures = rename_regex.captures(&description).ok_or_else(|| {
-                    anyhow!("Cannot parse file diff rename/copied paths: {description}")
-                })?;
-                let path_start = captures
-                    .get(1)
-                    .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path start"))?
-                    .as_str();
-                let path_new_end = captures
-                    .get(3)
-                    .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path new end"))?
-                    .as_str();
-
-                format!("{path_start}{path_new_end}")
-            }
-            _ => description.clone(),
+impl Comparison {
+    fn load(global_args: &GlobalArgs, from: String, to: String) -> Result<Self> {
+        let diff_revisions = DiffRevisions::FromTo {
+            from: from.clone(),
+            to: to.clone(),
         };
-
+        let file_diffs = FileDiff::load_all(global_args, &diff_revisions, "  ")?;
         Ok(Self {
-            change_id,
-            path,
-            description,
-            status,
-            graph_indent,
-            unfolded: false,
-            loaded: false,
-            diff_hunks: Vec::new(),
+            from,
+            to,
+            unfolded: true,
+            file_diffs,
             flat_log_idx: 0,
         })
     }
-
-    fn load_all(
-        global_args: &GlobalArgs,
-        change_id: &str,
-        graph_indent: &str,
-    ) -> Result<Vec<Self>> {
-        let output = JjCommand::diff_summary(change_id, global_args.clone()).run()?;
-        let lines: Vec<&str> = output.trim().lines().collect();
-
-        let mut file_diffs = Vec::new();
-        for line in lines {
-            file_diffs.push(Self::new(
-                change_id.to_string(),
-                line.to_string(),
-                graph_indent.to_string(),
-            )?);
-        }
-
-        Ok(file_diffs)
-    }
 }
 
-impl LogTreeNode for FileDiff {
+impl LogTreeNode for Comparison {
     fn render(&self) -> Result<Text<'static>> {
+        let num_files = match self.file_diffs.len() {
+            0 => "no changes".to_string(),
+            1 => "1 file".to_string(),
+            n => format!("{n} files"),
+        };
         let line = Line::from(vec![
-            Span::raw(self.graph_indent.clone()),
             fold_symbol(self.unfolded),
             Span::raw(" "),
+            Span::styled("Compare ", Style::default().fg(Color::Yellow)),
+            Span::styled(self.from.clone(), Style::default().fg(Color::Magenta)),
+            Span::raw(" → "),
+            Span::styled(self.to.clone(), Style::default().fg(Color::Magenta)),
             Span::styled(
-                format!("{}  {}", self.status, self.description),
-                Style::default().fg(Color::LightBlue),
+                format!(" ({num_files})"),
+                Style::default().fg(Color::DarkGray),
             ),
         ]);
         Ok(Text::from(line))
@@ -577,10 +896,10 @@ impl LogTreeNode for FileDiff {
             return Ok(());
         }
 
-        for (diff_hunk_idx, diff_hunk) in self.diff_hunks.iter_mut().enumerate() {
+        for (file_diff_idx, file_diff) in self.file_diffs.iter_mut().enumerate() {
             let mut new_pos = tree_pos.clone();
-            new_pos.push(diff_hunk_idx);
-            diff_hunk.flatten(new_pos, log_list, log_list_tree_positions)?;
+            new_pos.push(file_diff_idx);
+            file_diff.flatten(new_pos, log_list, log_list_tree_positions)?;
         }
 
         Ok(())
@@ -591,42 +910,1185 @@ impl LogTreeNode for FileDiff {
     }
 
     fn children(&self) -> Vec<&dyn LogTreeNode> {
-        self.diff_hunks
+        self.file_diffs
             .iter()
-            .map(|dh| dh as &dyn LogTreeNode)
+            .map(|fd| fd as &dyn LogTreeNode)
             .collect()
     }
 
-    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.file_diffs
+            .iter_mut()
+            .map(|fd| fd as &mut dyn LogTreeNode)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
         self.unfolded = !self.unfolded;
+        Ok(())
+    }
 
-        if !self.loaded {
-            let diff_hunks =
-                DiffHunk::load_all(global_args, &self.change_id, &self.path, &self.graph_indent)?;
-            self.diff_hunks = diff_hunks;
-            self.loaded = true;
-        }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 
-        Ok(())
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
     }
 }
 
+// Pairs the changes of a stack at an old operation with the same changes now, by change id
 #[derive(Debug)]
-enum FileDiffStatus {
-    Modified,
-    Added,
-    Deleted,
-    Renamed,
-    Copied,
+pub struct RangeDiff {
+    old_operation: String,
+    revset: String,
+    unfolded: bool,
+    entries: Vec<RangeDiffEntry>,
+    flat_log_idx: usize,
 }
 
-impl std::str::FromStr for FileDiffStatus {
-    type Err = Error;
+impl RangeDiff {
+    fn load(global_args: &GlobalArgs, old_operation: String, revset: String) -> Result<Self> {
+        let load_changes = |at_operation| -> Result<Vec<(String, String, String)>> {
+            let output =
+                JjCommand::stack_changes(&revset, at_operation, global_args.clone()).run()?;
+            strip_ansi(&output)
+                .lines()
+                .map(|line| {
+                    let mut fields = line.splitn(3, ' ');
+                    match (fields.next(), fields.next(), fields.next()) {
+                        (Some(change_id), Some(commit_id), description) => Ok((
+                            change_id.to_string(),
+                            commit_id.to_string(),
+                            description.unwrap_or_default().to_string(),
+                        )),
+                        _ => Err(anyhow!("Cannot parse stack change: {line:?}")),
+                    }
+                })
+                .collect()
+        };
+        let old_changes = load_changes(Some(&old_operation))?;
+        let new_changes = load_changes(None)?;
+
+        // Removed changes go where they were in the old stack, before the next change that's still
+        // there
+        let mut removed_before: HashMap<&str, Vec<RangeDiffEntry>> = HashMap::new();
+        let mut removed = Vec::new();
+        for (change_id, commit_id, description) in &old_changes {
+            if new_changes
+                .iter()
+                .any(|(new_change_id, _, _)| new_change_id == change_id)
+            {
+                removed_before.insert(change_id, mem::take(&mut removed));
+            } else {
+                let status = RangeDiffStatus::Removed {
+                    old_commit_id: commit_id.clone(),
+                };
+                removed.push(RangeDiffEntry::new(change_id, description, status));
+            }
+        }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "M" => Ok(FileDiffStatus::Modified),
-            "A" => Ok(FileDiffStatus::Added),
+        let mut entries = Vec::new();
+        for (change_id, commit_id, description) in &new_changes {
+            entries.extend(
+                removed_before
+                    .remove(change_id.as_str())
+                    .unwrap_or_default(),
+            );
+            let old_commit_id = old_changes
+                .iter()
+                .find(|(old_change_id, _, _)| old_change_id == change_id)
+                .map(|(_, old_commit_id, _)| old_commit_id);
+            let status = match old_commit_id {
+                None => RangeDiffStatus::Added {
+                    commit_id: commit_id.clone(),
+                },
+                Some(old_commit_id) if old_commit_id == commit_id => RangeDiffStatus::Unchanged,
+                Some(old_commit_id) => RangeDiffStatus::Changed {
+                    old_commit_id: old_commit_id.clone(),
+                    commit_id: commit_id.clone(),
+                },
+            };
+            entries.push(RangeDiffEntry::new(change_id, description, status));
+        }
+        entries.extend(removed);
+
+        Ok(Self {
+            old_operation,
+            revset,
+            unfolded: true,
+            entries,
+            flat_log_idx: 0,
+        })
+    }
+}
+
+impl LogTreeNode for RangeDiff {
+    fn render(&self) -> Result<Text<'static>> {
+        let line = Line::from(vec![
+            fold_symbol(self.unfolded),
+            Span::raw(" "),
+            Span::styled("Range-diff ", Style::default().fg(Color::Yellow)),
+            Span::styled(self.revset.clone(), Style::default().fg(Color::Magenta)),
+            Span::raw(" at operation "),
+            Span::styled(self.old_operation.clone(), Style::default().fg(Color::Blue)),
+            Span::raw(" → now"),
+            Span::styled(
+                format!(" ({} changes)", self.entries.len()),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]);
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        for (entry_idx, entry) in self.entries.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(entry_idx);
+            entry.flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        self.entries.iter().map(|e| e as &dyn LogTreeNode).collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.entries
+            .iter_mut()
+            .map(|e| e as &mut dyn LogTreeNode)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
+        self.unfolded = !self.unfolded;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+enum RangeDiffStatus {
+    Unchanged,
+    Changed {
+        old_commit_id: String,
+        commit_id: String,
+    },
+    Added {
+        commit_id: String,
+    },
+    Removed {
+        old_commit_id: String,
+    },
+}
+
+impl RangeDiffStatus {
+    // Unchanged changes have nothing to show
+    fn diff_revisions(&self) -> Option<DiffRevisions> {
+        match self {
+            Self::Unchanged => None,
+            Self::Changed {
+                old_commit_id,
+                commit_id,
+            } => Some(DiffRevisions::Interdiff {
+                from: old_commit_id.clone(),
+                to: commit_id.clone(),
+            }),
+            Self::Added { commit_id } => Some(DiffRevisions::Change(commit_id.clone())),
+            Self::Removed { old_commit_id } => Some(DiffRevisions::Change(old_commit_id.clone())),
+        }
+    }
+}
+
+impl fmt::Display for RangeDiffStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            Self::Unchanged => "unchanged",
+            Self::Changed { .. } => "changed",
+            Self::Added { .. } => "added",
+            Self::Removed { .. } => "removed",
+        };
+        write!(f, "{status:9}")
+    }
+}
+
+#[derive(Debug)]
+pub struct RangeDiffEntry {
+    change_id: String,
+    description: String,
+    status: RangeDiffStatus,
+    unfolded: bool,
+    loaded: bool,
+    file_diffs: Vec<FileDiff>,
+    flat_log_idx: usize,
+}
+
+impl RangeDiffEntry {
+    fn new(change_id: &str, description: &str, status: RangeDiffStatus) -> Self {
+        Self {
+            change_id: change_id.to_string(),
+            description: description.to_string(),
+            status,
+            unfolded: false,
+            loaded: false,
+            file_diffs: Vec::new(),
+            flat_log_idx: 0,
+        }
+    }
+}
+
+impl LogTreeNode for RangeDiffEntry {
+    fn render(&self) -> Result<Text<'static>> {
+        let status_color = match self.status {
+            RangeDiffStatus::Unchanged => Color::DarkGray,
+            RangeDiffStatus::Changed { .. } => Color::Yellow,
+            RangeDiffStatus::Added { .. } => Color::Green,
+            RangeDiffStatus::Removed { .. } => Color::Red,
+        };
+        let description = if self.description.is_empty() {
+            Span::styled("(no description set)", Style::default().fg(Color::DarkGray))
+        } else {
+            Span::raw(self.description.clone())
+        };
+        let line = Line::from(vec![
+            Span::raw("  "),
+            fold_symbol(self.unfolded),
+            Span::raw(" "),
+            Span::styled(
+                self.change_id.chars().take(8).collect::<String>(),
+                Style::default().fg(Color::Magenta),
+            ),
+            Span::raw(" "),
+            Span::styled(self.status.to_string(), Style::default().fg(status_color)),
+            Span::raw(" "),
+            description,
+        ]);
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        for (file_diff_idx, file_diff) in self.file_diffs.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(file_diff_idx);
+            file_diff.flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        self.file_diffs
+            .iter()
+            .map(|fd| fd as &dyn LogTreeNode)
+            .collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.file_diffs
+            .iter_mut()
+            .map(|fd| fd as &mut dyn LogTreeNode)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        self.unfolded = !self.unfolded;
+
+        if !self.loaded {
+            if let Some(diff_revisions) = self.status.diff_revisions() {
+                self.file_diffs = FileDiff::load_all(global_args, &diff_revisions, "    ")?;
+            }
+            self.loaded = true;
+        }
+
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Evolog {
+    change_id: String,
+    graph_indent: String,
+    unfolded: bool,
+    loaded: bool,
+    entries: Vec<EvologEntry>,
+    flat_log_idx: usize,
+}
+
+impl Evolog {
+    fn new(change_id: String, graph_indent: String) -> Self {
+        Self {
+            change_id,
+            graph_indent,
+            unfolded: false,
+            loaded: false,
+            entries: Vec::new(),
+            flat_log_idx: 0,
+        }
+    }
+}
+
+impl LogTreeNode for Evolog {
+    fn render(&self) -> Result<Text<'static>> {
+        let line = Line::from(vec![
+            Span::raw(self.graph_indent.clone()),
+            fold_symbol(self.unfolded),
+            Span::raw(" "),
+            Span::styled("Evolution log", Style::default().fg(Color::Yellow)),
+        ]);
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        for (entry_idx, entry) in self.entries.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(entry_idx);
+            entry.flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        self.entries.iter().map(|e| e as &dyn LogTreeNode).collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.entries
+            .iter_mut()
+            .map(|e| e as &mut dyn LogTreeNode)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        self.unfolded = !self.unfolded;
+
+        if !self.loaded {
+            let entries = EvologEntry::load_all(global_args, &self.change_id, &self.graph_indent)?;
+            self.entries = entries;
+            self.loaded = true;
+        }
+
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// The change id and commit id of a version of a change
+type VersionIds = (String, String);
+
+#[derive(Debug)]
+pub struct EvologEntry {
+    pub change_id: String,
+    pub commit_id: String,
+    // The change this version belonged to, which differs from change_id for squashed-in versions
+    version_change_id: String,
+    // Change id and commit id of each version this one was rewritten from. Squashes and divergent
+    // rewrites leave more than one.
+    predecessors: Vec<VersionIds>,
+    pretty_lines: Vec<String>,
+    graph_indent: String,
+    unfolded: bool,
+    loaded: bool,
+    file_diffs: Vec<FileDiff>,
+    flat_log_idx: usize,
+}
+
+impl EvologEntry {
+    fn new(
+        change_id: String,
+        (version_change_id, commit_id): VersionIds,
+        predecessors: Vec<VersionIds>,
+        pretty_lines: Vec<String>,
+        graph_indent: String,
+    ) -> Self {
+        Self {
+            change_id,
+            commit_id,
+            version_change_id,
+            predecessors,
+            pretty_lines,
+            graph_indent,
+            unfolded: false,
+            loaded: false,
+            file_diffs: Vec::new(),
+            flat_log_idx: 0,
+        }
+    }
+
+    fn load_all(
+        global_args: &GlobalArgs,
+        change_id: &str,
+        graph_indent: &str,
+    ) -> Result<Vec<Self>> {
+        let output = JjCommand::evolog_predecessors(change_id, global_args.clone()).run()?;
+        let versions = strip_ansi(&output)
+            .lines()
+            .map(Self::parse_version)
+            .collect::<Result<Vec<_>>>()?;
+
+        // The pretty output is only for display. Each entry starts with a node line showing its
+        // change id and commit id, followed by its description and operation lines.
+        let output = JjCommand::evolog(change_id, global_args.clone()).run()?;
+        let mut entries_lines: Vec<Vec<String>> = Vec::new();
+        for line in output.trim_end().lines() {
+            let starts_next_entry = versions
+                .get(entries_lines.len())
+                .is_some_and(|(ids, _)| is_node_line(&strip_ansi(line), ids));
+            match entries_lines.last_mut() {
+                Some(entry_lines) if !starts_next_entry => entry_lines.push(line.to_string()),
+                _ => entries_lines.push(vec![line.to_string()]),
+            }
+        }
+        if entries_lines.len() != versions.len() {
+            return Err(anyhow!(
+                "Cannot match the evolog of {change_id} to its {} versions",
+                versions.len()
+            ));
+        }
+
+        let graph_indent = format!("{graph_indent}  ");
+        Ok(versions
+            .into_iter()
+            .zip(entries_lines)
+            .map(|((ids, predecessors), entry_lines)| {
+                Self::new(
+                    change_id.to_string(),
+                    ids,
+                    predecessors,
+                    entry_lines,
+                    graph_indent.clone(),
+                )
+            })
+            .collect())
+    }
+
+    // Returns the version's change id and commit id, and those of its predecessors
+    fn parse_version(line: &str) -> Result<(VersionIds, Vec<VersionIds>)> {
+        let mut fields = line.split(' ');
+        let (Some(version_change_id), Some(commit_id)) = (fields.next(), fields.next()) else {
+            return Err(anyhow!("Cannot parse evolog version: {line:?}"));
+        };
+        let predecessors = fields
+            .map(|field| {
+                field
+                    .split_once(':')
+                    .map(|(change_id, commit_id)| (change_id.to_string(), commit_id.to_string()))
+                    .ok_or_else(|| anyhow!("Cannot parse evolog predecessor: {field:?}"))
+            })
+            .collect::<Result<_>>()?;
+        Ok((
+            (version_change_id.to_string(), commit_id.to_string()),
+            predecessors,
+        ))
+    }
+
+    fn diff_revisions(&self) -> DiffRevisions {
+        // The version of the same change it was rewritten from, rather than one squashed into it
+        let same_change_predecessor = self
+            .predecessors
+            .iter()
+            .find(|(change_id, _)| *change_id == self.version_change_id);
+        match (same_change_predecessor, self.predecessors.first()) {
+            (Some((_, predecessor_commit_id)), _) => DiffRevisions::Interdiff {
+                from: predecessor_commit_id.clone(),
+                to: self.commit_id.clone(),
+            },
+            // Only other changes went into it, so show what changed from the first of them
+            (None, Some((_, predecessor_commit_id))) => DiffRevisions::FromTo {
+                from: predecessor_commit_id.clone(),
+                to: self.commit_id.clone(),
+            },
+            // The first version has nothing to compare against, so show the change as created
+            (None, None) => DiffRevisions::Change(self.commit_id.clone()),
+        }
+    }
+}
+
+// Whether a line of jj's pretty output is the node line of a version: after the graph, it starts
+// with the change id and later shows the commit id, both shortened
+fn is_node_line(line: &str, (change_id, commit_id): &VersionIds) -> bool {
+    let is_prefix = |word: &str, id: &str| word.len() >= 8 && id.starts_with(word);
+    let mut words = line
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .split_whitespace();
+    words.next().is_some_and(|word| is_prefix(word, change_id))
+        && words.any(|word| is_prefix(word, commit_id))
+}
+
+impl LogTreeNode for EvologEntry {
+    fn render(&self) -> Result<Text<'static>> {
+        let mut lines = Vec::new();
+        for (line_idx, pretty_line) in self.pretty_lines.iter().enumerate() {
+            let mut line = Line::from(vec![Span::raw(self.graph_indent.clone())]);
+            if line_idx == 0 {
+                line.push_span(fold_symbol(self.unfolded));
+                line.push_span(Span::raw(" "));
+            } else {
+                line.push_span(Span::raw("  "));
+            }
+            if let Some(pretty_line) = pretty_line.into_text()?.lines.first() {
+                line.extend(pretty_line.spans.clone());
+            }
+            lines.push(line);
+        }
+        Ok(Text::from(lines))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        for (file_diff_idx, file_diff) in self.file_diffs.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(file_diff_idx);
+            file_diff.flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        self.file_diffs
+            .iter()
+            .map(|fd| fd as &dyn LogTreeNode)
+            .collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.file_diffs
+            .iter_mut()
+            .map(|fd| fd as &mut dyn LogTreeNode)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        self.unfolded = !self.unfolded;
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        if !self.loaded {
+            let graph_indent = format!("{}  ", self.graph_indent);
+            let file_diffs =
+                FileDiff::load_all(global_args, &self.diff_revisions(), &graph_indent)?;
+            self.file_diffs = file_diffs;
+            self.loaded = true;
+        }
+
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct FileTree {
+    change_id: String,
+    graph_indent: String,
+    unfolded: bool,
+    loaded: bool,
+    entries: Vec<FileTreeEntry>,
+    flat_log_idx: usize,
+}
+
+impl FileTree {
+    fn new(change_id: String, graph_indent: String) -> Self {
+        Self {
+            change_id,
+            graph_indent,
+            unfolded: false,
+            loaded: false,
+            entries: Vec::new(),
+            flat_log_idx: 0,
+        }
+    }
+}
+
+impl LogTreeNode for FileTree {
+    fn render(&self) -> Result<Text<'static>> {
+        let line = Line::from(vec![
+            Span::raw(self.graph_indent.clone()),
+            fold_symbol(self.unfolded),
+            Span::raw(" "),
+            Span::styled("File tree", Style::default().fg(Color::Yellow)),
+        ]);
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        for (entry_idx, entry) in self.entries.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(entry_idx);
+            entry
+                .as_node_mut()
+                .flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        self.entries.iter().map(FileTreeEntry::as_node).collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.entries
+            .iter_mut()
+            .map(FileTreeEntry::as_node_mut)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        self.unfolded = !self.unfolded;
+
+        if !self.loaded {
+            let output = JjCommand::file_list(&self.change_id, global_args.clone()).run()?;
+            let output = strip_ansi(&output);
+            let paths: Vec<&str> = output.lines().filter(|line| !line.is_empty()).collect();
+            self.entries = FileTreeEntry::build_all(
+                &self.change_id,
+                "",
+                &paths,
+                &format!("{}  ", self.graph_indent),
+            );
+            self.loaded = true;
+        }
+
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+enum FileTreeEntry {
+    Dir(FileTreeDir),
+    File(FileTreeFile),
+}
+
+impl FileTreeEntry {
+    // Builds the entries of a directory from the paths under it, relative to it. Directories are
+    // listed before files, each sorted by name.
+    fn build_all(change_id: &str, dir_path: &str, paths: &[&str], graph_indent: &str) -> Vec<Self> {
+        let mut subdirs: Vec<(&str, Vec<&str>)> = Vec::new();
+        let mut files = Vec::new();
+        for path in paths {
+            match path.split_once('/') {
+                None => files.push(*path),
+                Some((name, rest)) => match subdirs.iter_mut().find(|(n, _)| *n == name) {
+                    Some((_, subdir_paths)) => subdir_paths.push(rest),
+                    None => subdirs.push((name, vec![rest])),
+                },
+            }
+        }
+        subdirs.sort_by_key(|(name, _)| *name);
+        files.sort();
+
+        let mut entries = Vec::new();
+        for (name, subdir_paths) in subdirs {
+            let path = format!("{dir_path}{name}/");
+            entries.push(Self::Dir(FileTreeDir {
+                name: name.to_string(),
+                entries: Self::build_all(
+                    change_id,
+                    &path,
+                    &subdir_paths,
+                    &format!("{graph_indent}  "),
+                ),
+                graph_indent: graph_indent.to_string(),
+                unfolded: false,
+                flat_log_idx: 0,
+            }));
+        }
+        for name in files {
+            entries.push(Self::File(FileTreeFile {
+                change_id: change_id.to_string(),
+                path: format!("{dir_path}{name}"),
+                name: name.to_string(),
+                graph_indent: graph_indent.to_string(),
+                flat_log_idx: 0,
+            }));
+        }
+        entries
+    }
+
+    fn as_node(&self) -> &dyn LogTreeNode {
+        match self {
+            Self::Dir(dir) => dir,
+            Self::File(file) => file,
+        }
+    }
+
+    fn as_node_mut(&mut self) -> &mut dyn LogTreeNode {
+        match self {
+            Self::Dir(dir) => dir,
+            Self::File(file) => file,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FileTreeDir {
+    name: String,
+    graph_indent: String,
+    unfolded: bool,
+    entries: Vec<FileTreeEntry>,
+    flat_log_idx: usize,
+}
+
+impl LogTreeNode for FileTreeDir {
+    fn render(&self) -> Result<Text<'static>> {
+        let line = Line::from(vec![
+            Span::raw(self.graph_indent.clone()),
+            fold_symbol(self.unfolded),
+            Span::raw(" "),
+            Span::styled(format!("{}/", self.name), Style::default().fg(Color::Blue)),
+        ]);
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        for (entry_idx, entry) in self.entries.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(entry_idx);
+            entry
+                .as_node_mut()
+                .flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        self.entries.iter().map(FileTreeEntry::as_node).collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.entries
+            .iter_mut()
+            .map(FileTreeEntry::as_node_mut)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
+        self.unfolded = !self.unfolded;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct FileTreeFile {
+    pub change_id: String,
+    pub path: String,
+    name: String,
+    graph_indent: String,
+    flat_log_idx: usize,
+}
+
+impl LogTreeNode for FileTreeFile {
+    fn render(&self) -> Result<Text<'static>> {
+        let line = Line::from(vec![
+            Span::raw(self.graph_indent.clone()),
+            Span::raw("  "),
+            Span::raw(self.name.clone()),
+        ]);
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        Vec::new()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        Vec::new()
+    }
+
+    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct FileDiff {
+    pub diff_revisions: DiffRevisions,
+    pub path: String,
+    description: String,
+    status: FileDiffStatus,
+    graph_indent: String,
+    unfolded: bool,
+    loaded: bool,
+    diff_hunks: Vec<DiffHunk>,
+    side_by_side_width: Option<u16>,
+    flat_log_idx: usize,
+}
+
+impl FileDiff {
+    pub fn new(
+        diff_revisions: DiffRevisions,
+        pretty_string: String,
+        graph_indent: String,
+    ) -> Result<Self> {
+        let clean_string = strip_ansi(&pretty_string);
+        let re = Regex::new(r"^([MADRC])\s+(.+)$").unwrap();
+
+        let captures = re
+            .captures(&clean_string)
+            .ok_or_else(|| anyhow!("Cannot parse file diff string: {clean_string}"))?;
+        let status = captures
+            .get(1)
+            .ok_or_else(|| anyhow!("Cannot parse file diff status"))?
+            .as_str()
+            .parse::<FileDiffStatus>()?;
+        let description: String = captures
+            .get(2)
+            .ok_or_else(|| anyhow!("Cannot parse file diff path"))?
+            .as_str()
+            .into();
+
+        let path = match status {
+            FileDiffStatus::Renamed | FileDiffStatus::Copied => {
+                let rename_regex = Regex::new(r"^(.+)\{(.+?)\s*=>\s*(.+?)\}$").unwrap();
+                let captures = rename_regex.captures(&description).ok_or_else(|| {
+                    anyhow!("Cannot parse file diff rename/copied paths: {description}")
+                })?;
+                let path_start = captures
+                    .get(1)
+                    .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path start"))?
+                    .as_str();
+                let path_new_end = captures
+                    .get(3)
+                    .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path new end"))?
+                    .as_str();
+
+                format!("{path_start}{path_new_end}")
+            }
+            _ => description.clone(),
+        };
+
+        Ok(Self {
+            diff_revisions,
+            path,
+            description,
+            status,
+            graph_indent,
+            unfolded: false,
+            loaded: false,
+            diff_hunks: Vec::new(),
+            side_by_side_width: None,
+            flat_log_idx: 0,
+        })
+    }
+
+    fn set_side_by_side(&mut self, width: Option<u16>) {
+        self.side_by_side_width = width;
+        for diff_hunk in self.diff_hunks.iter_mut() {
+            diff_hunk.set_side_by_side(width);
+        }
+    }
+
+    fn load_diff_hunks(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        self.diff_hunks = DiffHunk::load_all(
+            global_args,
+            &self.diff_revisions,
+            &self.path,
+            &self.graph_indent,
+        )?;
+        self.set_side_by_side(self.side_by_side_width);
+        Ok(())
+    }
+
+    fn load_all(
+        global_args: &GlobalArgs,
+        diff_revisions: &DiffRevisions,
+        graph_indent: &str,
+    ) -> Result<Vec<Self>> {
+        let output = JjCommand::diff_summary(diff_revisions, global_args.clone()).run()?;
+        let lines: Vec<&str> = output.trim().lines().collect();
+
+        let mut file_diffs = Vec::new();
+        for line in lines {
+            file_diffs.push(Self::new(
+                diff_revisions.clone(),
+                line.to_string(),
+                graph_indent.to_string(),
+            )?);
+        }
+
+        Ok(file_diffs)
+    }
+}
+
+impl LogTreeNode for FileDiff {
+    fn render(&self) -> Result<Text<'static>> {
+        let line = Line::from(vec![
+            Span::raw(self.graph_indent.clone()),
+            fold_symbol(self.unfolded),
+            Span::raw(" "),
+            Span::styled(
+                format!("{}  {}", self.status, self.description),
+                Style::default().fg(Color::LightBlue),
+            ),
+        ]);
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos.clone());
+
+        if !self.unfolded {
+            return Ok(());
+        }
+
+        for (diff_hunk_idx, diff_hunk) in self.diff_hunks.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(diff_hunk_idx);
+            diff_hunk.flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        self.diff_hunks
+            .iter()
+            .map(|dh| dh as &dyn LogTreeNode)
+            .collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        self.diff_hunks
+            .iter_mut()
+            .map(|dh| dh as &mut dyn LogTreeNode)
+            .collect()
+    }
+
+    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        self.unfolded = !self.unfolded;
+
+        if !self.loaded {
+            self.load_diff_hunks(global_args)?;
+            self.loaded = true;
+        }
+
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn reload_diffs(&mut self, global_args: &GlobalArgs) -> Result<()> {
+        if self.loaded {
+            self.load_diff_hunks(global_args)?;
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u16) {
+        if self.side_by_side_width.is_some() {
+            self.set_side_by_side(Some(width));
+        }
+    }
+}
+
+#[derive(Debug)]
+enum FileDiffStatus {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Copied,
+}
+
+impl std::str::FromStr for FileDiffStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "M" => Ok(FileDiffStatus::Modified),
+            "A" => Ok(FileDiffStatus::Added),
             "D" => Ok(FileDiffStatus::Deleted),
             "R" => Ok(FileDiffStatus::Renamed),
             "C" => Ok(FileDiffStatus::Copied),
@@ -649,174 +2111,279 @@ impl fmt::Display for FileDiffStatus {
 }
 
 #[derive(Debug)]
-struct DiffHunk {
+pub struct DiffHunk {
     graph_indent: String,
     unfolded: bool,
     diff_hunk_lines: Vec<DiffHunkLine>,
-    red_start: u32,
-    red_end: u32,
-    green_start: u32,
-    green_end: u32,
+    old_start: u32,
+    old_len: u32,
+    new_start: u32,
+    new_len: u32,
+    // Text following the range header, usually the enclosing function
+    section_heading: String,
+    side_by_side_rows: Option<Vec<SideBySideRow>>,
     flat_log_idx: usize,
 }
 
-enum SearchDirection {
-    Down,
-    Up,
-}
-
 impl DiffHunk {
-    fn new(graph_indent: String, diff_hunk_lines: Vec<DiffHunkLine>) -> Result<Self> {
-        let (red_start, green_start) =
-            Self::find_line_nums(&diff_hunk_lines, SearchDirection::Down)?;
-        let (red_end, green_end) = Self::find_line_nums(&diff_hunk_lines, SearchDirection::Up)?;
-
-        // Align line nums
-        let max_line_num = red_end.max(green_end);
-        let line_num_chars_len = max_line_num.ilog10() as usize;
-        let mut diff_hunk_lines = diff_hunk_lines;
-        for line in diff_hunk_lines.iter_mut() {
-            line.pretty_string = line.pretty_string.replacen(
-                &" ".repeat(3_usize.saturating_sub(line_num_chars_len)),
-                "",
-                1,
-            );
-        }
+    fn new(graph_indent: String, header: &str) -> Result<Self> {
+        let header_regex = Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@ ?(.*)$")?;
+        let captures = header_regex
+            .captures(header)
+            .ok_or_else(|| anyhow!("Cannot parse diff hunk header: {header:?}"))?;
+
+        // A range's length is omitted when it's 1
+        let parse_num = |idx: usize, default: u32| -> Result<u32> {
+            match captures.get(idx) {
+                None => Ok(default),
+                Some(num_match) => Ok(num_match.as_str().parse()?),
+            }
+        };
 
         Ok(Self {
             graph_indent,
             unfolded: true,
-            diff_hunk_lines,
-            red_start,
-            red_end,
-            green_start,
-            green_end,
+            diff_hunk_lines: Vec::new(),
+            old_start: parse_num(1, 0)?,
+            old_len: parse_num(2, 1)?,
+            new_start: parse_num(3, 0)?,
+            new_len: parse_num(4, 1)?,
+            section_heading: captures
+                .get(5)
+                .map_or(String::new(), |m| m.as_str().to_string()),
+            side_by_side_rows: None,
             flat_log_idx: 0,
         })
     }
 
-    fn find_line_nums(
-        diff_hunk_lines: &[DiffHunkLine],
-        direction: SearchDirection,
-    ) -> Result<(u32, u32)> {
-        let line_nums_regex = Regex::new(r"^\s*(\d+)?\s+(\d+)?:").unwrap();
-        let mut red: Option<String> = None;
-        let mut green: Option<String> = None;
+    fn load_all(
+        global_args: &GlobalArgs,
+        diff_revisions: &DiffRevisions,
+        file: &str,
+        graph_indent: &str,
+    ) -> Result<Vec<Self>> {
+        let output = JjCommand::diff_file(diff_revisions, file, global_args.clone()).run()?;
+        let mut diff_hunks = Self::parse_git(
+            &strip_ansi(&output),
+            graph_indent,
+            global_args.diff_options.format.unwrap_or_default(),
+        )?;
+
+        // Visual divider between hunk diff and next item in log list
+        if let Some(diff_hunk) = diff_hunks.last_mut() {
+            diff_hunk.diff_hunk_lines.push(DiffHunkLine::new(
+                DiffLineKind::Divider,
+                String::new(),
+                None,
+                None,
+                graph_indent.to_string(),
+            ));
+        }
 
-        let hunk_lines: Vec<&DiffHunkLine> = match direction {
-            SearchDirection::Down => diff_hunk_lines.iter().collect(),
-            SearchDirection::Up => diff_hunk_lines.iter().rev().collect(),
-        };
-        for line in hunk_lines.iter().map(|l| strip_ansi(&l.pretty_string)) {
-            if line == "~" {
+        Ok(diff_hunks)
+    }
+
+    fn parse_git(output: &str, graph_indent: &str, format: DiffFormat) -> Result<Vec<Self>> {
+        let mut diff_hunks: Vec<DiffHunk> = Vec::new();
+        let mut old_line_num = 0;
+        let mut new_line_num = 0;
+
+        // Lines before the first hunk header are the file header, which we don't display
+        for line in output.lines() {
+            if line.starts_with("@@") {
+                let diff_hunk = Self::new(graph_indent.to_string(), line)?;
+                old_line_num = diff_hunk.old_start;
+                new_line_num = diff_hunk.new_start;
+                diff_hunks.push(diff_hunk);
                 continue;
             }
-            let captures = line_nums_regex
-                .captures(&line)
-                .ok_or_else(|| anyhow!("Cannot parse diff hunk line: {line:?}"))?;
-            if red.is_none()
-                && let Some(num_match) = captures.get(1)
-                && !num_match.is_empty()
-            {
-                red = Some(num_match.as_str().to_string())
+            let Some(diff_hunk) = diff_hunks.last_mut() else {
+                continue;
+            };
+
+            let (kind, content) = match line.split_at_checked(1) {
+                Some(("+", content)) => (DiffLineKind::Added, content),
+                Some(("-", content)) => (DiffLineKind::Removed, content),
+                Some(("\\", content)) => (DiffLineKind::NoNewline, content.trim_start()),
+                Some((_, content)) => (DiffLineKind::Context, content),
+                None => (DiffLineKind::Context, ""),
+            };
+            let (old, new) = match kind {
+                DiffLineKind::Added => (None, Some(new_line_num)),
+                DiffLineKind::Removed => (Some(old_line_num), None),
+                DiffLineKind::Context => (Some(old_line_num), Some(new_line_num)),
+                DiffLineKind::NoNewline | DiffLineKind::Divider => (None, None),
+            };
+            if old.is_some() {
+                old_line_num += 1;
             }
-            if green.is_none()
-                && let Some(num_match) = captures.get(2)
-                && !num_match.is_empty()
-            {
-                green = Some(num_match.as_str().to_string())
+            if new.is_some() {
+                new_line_num += 1;
             }
 
-            if red.is_some() && green.is_some() {
-                break;
-            }
+            diff_hunk.diff_hunk_lines.push(DiffHunkLine::new(
+                kind,
+                content.to_string(),
+                old,
+                new,
+                graph_indent.to_string(),
+            ));
         }
 
-        if red.is_none() {
-            red = Some("0".to_string());
-        }
-        if green.is_none() {
-            green = Some("0".to_string());
+        for diff_hunk in diff_hunks.iter_mut() {
+            diff_hunk.align_line_nums(format);
         }
 
-        Ok((red.unwrap().parse()?, green.unwrap().parse()?))
+        Ok(diff_hunks)
+    }
+
+    fn old_end(&self) -> u32 {
+        (self.old_start + self.old_len).saturating_sub(1)
     }
 
-    fn load_all(
-        global_args: &GlobalArgs,
-        change_id: &str,
-        file: &str,
-        graph_indent: &str,
-    ) -> Result<Vec<Self>> {
-        let output = JjCommand::diff_file(change_id, file, global_args.clone()).run()?;
-        let output_lines: Vec<&str> = output.trim().lines().skip(1).collect();
+    fn new_end(&self) -> u32 {
+        (self.new_start + self.new_len).saturating_sub(1)
+    }
 
-        let separator_regex = Regex::new(r"\s*\.\.\.\s*")?;
-        let mut diff_hunks: Vec<DiffHunk> = Vec::new();
-        let mut diff_hunk_lines = Vec::new();
+    // Line numbers of the hunk in the new version of the file, as a start and length
+    pub fn new_range(&self) -> (u32, u32) {
+        (self.new_start, self.new_len)
+    }
 
-        let mut push_diff_hunk = |diff_hunk_lines: Vec<DiffHunkLine>| -> Result<()> {
-            if !diff_hunk_lines.is_empty() {
-                diff_hunks.push(Self::new(graph_indent.to_string(), diff_hunk_lines)?);
-            }
-            Ok(())
-        };
+    // The hunk as it appears in a git diff
+    pub fn patch_text(&self) -> String {
+        let mut text = format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start, self.old_len, self.new_start, self.new_len
+        );
+        if !self.section_heading.is_empty() {
+            text.push(' ');
+            text.push_str(&self.section_heading);
+        }
+        text.push('\n');
+        for patch_line in self
+            .diff_hunk_lines
+            .iter()
+            .filter_map(DiffHunkLine::patch_line)
+        {
+            text.push_str(&patch_line);
+            text.push('\n');
+        }
+        text
+    }
 
-        for line in output_lines {
-            let clean_line = strip_ansi(line);
+    fn set_side_by_side(&mut self, width: Option<u16>) {
+        self.side_by_side_rows = width.map(|width| self.build_side_by_side_rows(width));
+    }
 
-            if separator_regex.is_match(&clean_line) {
-                push_diff_hunk(diff_hunk_lines)?;
-                diff_hunk_lines = Vec::new();
-            } else {
-                diff_hunk_lines.push(DiffHunkLine::new(
-                    line.to_string(),
-                    graph_indent.to_string(),
+    // Pairs up each run of removed lines with the added lines that follow it
+    fn build_side_by_side_rows(&self, width: u16) -> Vec<SideBySideRow> {
+        let mut rows = Vec::new();
+        // Lines are kept with their index, so each row knows which lines it shows
+        let mut removed: Vec<(usize, DiffHunkLine)> = Vec::new();
+        let mut added: Vec<(usize, DiffHunkLine)> = Vec::new();
+
+        let flush = |rows: &mut Vec<SideBySideRow>,
+                     removed: &mut Vec<(usize, DiffHunkLine)>,
+                     added: &mut Vec<(usize, DiffHunkLine)>| {
+            let num_rows = removed.len().max(added.len());
+            let mut removed = removed.drain(..);
+            let mut added = added.drain(..);
+            for _ in 0..num_rows {
+                let (old_idx, old_line) = removed.next().unzip();
+                let (new_idx, new_line) = added.next().unzip();
+                rows.push(SideBySideRow::new(
+                    old_line,
+                    new_line,
+                    old_idx.into_iter().chain(new_idx).collect(),
+                    self.graph_indent.clone(),
+                    width,
                 ));
             }
+        };
+
+        for (idx, line) in self.diff_hunk_lines.iter().enumerate() {
+            match line.kind {
+                DiffLineKind::Removed => {
+                    if !added.is_empty() {
+                        flush(&mut rows, &mut removed, &mut added);
+                    }
+                    removed.push((idx, line.clone()));
+                }
+                DiffLineKind::Added => added.push((idx, line.clone())),
+                DiffLineKind::NoNewline if !added.is_empty() => added.push((idx, line.clone())),
+                DiffLineKind::NoNewline if !removed.is_empty() => removed.push((idx, line.clone())),
+                DiffLineKind::Context | DiffLineKind::NoNewline => {
+                    flush(&mut rows, &mut removed, &mut added);
+                    rows.push(SideBySideRow::new(
+                        Some(line.clone()),
+                        Some(line.clone()),
+                        vec![idx],
+                        self.graph_indent.clone(),
+                        width,
+                    ));
+                }
+                DiffLineKind::Divider => {
+                    flush(&mut rows, &mut removed, &mut added);
+                    rows.push(SideBySideRow::new(
+                        Some(line.clone()),
+                        None,
+                        vec![idx],
+                        self.graph_indent.clone(),
+                        width,
+                    ));
+                }
+            }
         }
+        flush(&mut rows, &mut removed, &mut added);
 
-        push_diff_hunk(diff_hunk_lines)?;
+        rows
+    }
 
-        // Visual divider between hunk diff and next item in log list
-        diff_hunks
-            .last_mut()
-            .unwrap()
-            .diff_hunk_lines
-            .push(DiffHunkLine::new(
-                "\x1b[35m~\x1b[0m".to_string(),
-                graph_indent.to_string(),
-            ));
+    // The index of the diff line shown by a child, the first one for a side-by-side row
+    fn child_line_idx(&self, child_idx: usize) -> Option<usize> {
+        match &self.side_by_side_rows {
+            None => Some(child_idx),
+            Some(rows) => rows.get(child_idx)?.line_idxs.first().copied(),
+        }
+    }
 
-        Ok(diff_hunks)
+    fn line_child_idx(&self, line_idx: usize) -> Option<usize> {
+        match &self.side_by_side_rows {
+            None => Some(line_idx),
+            Some(rows) => rows
+                .iter()
+                .position(|row| row.line_idxs.contains(&line_idx)),
+        }
+    }
+
+    fn align_line_nums(&mut self, format: DiffFormat) {
+        let max_line_num = self.old_end().max(self.new_end()).max(1);
+        let line_num_width = max_line_num.ilog10() as usize + 1;
+        for line in self.diff_hunk_lines.iter_mut() {
+            line.format = format;
+            line.line_num_width = line_num_width;
+        }
     }
 }
 
 impl LogTreeNode for DiffHunk {
     fn render(&self) -> Result<Text<'static>> {
-        let red_num_lines = if self.red_end == 0 {
-            0
-        } else {
-            self.red_end - self.red_start + 1
-        };
-        let green_num_lines = if self.green_end == 0 {
-            0
-        } else {
-            self.green_end - self.green_start + 1
-        };
-
-        let line = Line::from(vec![
+        let mut line = Line::from(vec![
             Span::raw(self.graph_indent.clone()),
             fold_symbol(self.unfolded),
             Span::raw(" "),
             Span::styled(
                 format!(
                     "@@ -{},{} +{},{} @@",
-                    self.red_start, red_num_lines, self.green_start, green_num_lines,
+                    self.old_start, self.old_len, self.new_start, self.new_len,
                 ),
                 Style::default().fg(Color::Magenta),
             ),
         ]);
+        if !self.section_heading.is_empty() {
+            line.push_span(Span::raw(format!(" {}", self.section_heading)));
+        }
         Ok(Text::from(line))
     }
 
@@ -834,10 +2401,10 @@ impl LogTreeNode for DiffHunk {
             return Ok(());
         }
 
-        for (diff_hunk_line_idx, diff_hunk_line) in self.diff_hunk_lines.iter_mut().enumerate() {
+        for (child_idx, child) in self.children_mut().into_iter().enumerate() {
             let mut new_pos = tree_pos.clone();
-            new_pos.push(diff_hunk_line_idx);
-            diff_hunk_line.flatten(new_pos, log_list, log_list_tree_positions)?;
+            new_pos.push(child_idx);
+            child.flatten(new_pos, log_list, log_list_tree_positions)?;
         }
 
         Ok(())
@@ -848,50 +2415,317 @@ impl LogTreeNode for DiffHunk {
     }
 
     fn children(&self) -> Vec<&dyn LogTreeNode> {
-        self.diff_hunk_lines
-            .iter()
-            .map(|hl| hl as &dyn LogTreeNode)
-            .collect()
+        match &self.side_by_side_rows {
+            None => self
+                .diff_hunk_lines
+                .iter()
+                .map(|hl| hl as &dyn LogTreeNode)
+                .collect(),
+            Some(rows) => rows.iter().map(|r| r as &dyn LogTreeNode).collect(),
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        match &mut self.side_by_side_rows {
+            None => self
+                .diff_hunk_lines
+                .iter_mut()
+                .map(|hl| hl as &mut dyn LogTreeNode)
+                .collect(),
+            Some(rows) => rows.iter_mut().map(|r| r as &mut dyn LogTreeNode).collect(),
+        }
     }
 
     fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
         self.unfolded = !self.unfolded;
         Ok(())
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+    NoNewline,
+    Divider,
+}
+
+#[derive(Debug, Clone)]
 struct DiffHunkLine {
-    pretty_string: String,
+    kind: DiffLineKind,
+    content: String,
+    old_line_num: Option<u32>,
+    new_line_num: Option<u32>,
+    format: DiffFormat,
+    line_num_width: usize,
     graph_indent: String,
     flat_log_idx: usize,
 }
 
 impl DiffHunkLine {
-    fn new(pretty_string: String, graph_indent: String) -> Self {
+    fn new(
+        kind: DiffLineKind,
+        content: String,
+        old_line_num: Option<u32>,
+        new_line_num: Option<u32>,
+        graph_indent: String,
+    ) -> Self {
         Self {
-            pretty_string,
+            kind,
+            content,
+            old_line_num,
+            new_line_num,
+            format: DiffFormat::default(),
+            line_num_width: 1,
             graph_indent,
             flat_log_idx: 0,
         }
     }
+
+    fn patch_line(&self) -> Option<String> {
+        let marker = match self.kind {
+            DiffLineKind::Context => " ",
+            DiffLineKind::Added => "+",
+            DiffLineKind::Removed => "-",
+            DiffLineKind::NoNewline => "\\ ",
+            DiffLineKind::Divider => return None,
+        };
+        Some(format!("{marker}{}", self.content))
+    }
+
+    fn content_style(&self) -> Style {
+        match self.kind {
+            DiffLineKind::Added => Style::default().fg(Color::Green).bold(),
+            DiffLineKind::Removed => Style::default().fg(Color::Red).bold(),
+            DiffLineKind::NoNewline => Style::default().fg(Color::DarkGray).italic(),
+            DiffLineKind::Context | DiffLineKind::Divider => Style::default(),
+        }
+    }
+
+    // Old and new line numbers, as shown by jj's color-words format
+    fn line_num_spans(&self) -> Vec<Span<'static>> {
+        let width = self.line_num_width;
+        let line_num = |line_num: Option<u32>, color: Color| -> Span<'static> {
+            let text = match line_num {
+                None => " ".repeat(width),
+                Some(line_num) => format!("{line_num:>width$}"),
+            };
+            match self.kind {
+                DiffLineKind::Context => Span::styled(text, Style::default().fg(Color::DarkGray)),
+                _ => Span::styled(text, Style::default().fg(color)),
+            }
+        };
+        vec![
+            line_num(self.old_line_num, Color::Red),
+            Span::raw(" "),
+            line_num(self.new_line_num, Color::Green),
+            Span::raw(": "),
+        ]
+    }
 }
 
 impl LogTreeNode for DiffHunkLine {
     fn render(&self) -> Result<Text<'static>> {
-        let clean_string = strip_ansi(&self.pretty_string);
         let mut line = Line::from(vec![Span::raw(self.graph_indent.clone()), Span::raw("  ")]);
 
-        for span in self.pretty_string.into_text()?.lines[0].spans.clone() {
-            let span = if clean_string.starts_with("+") || clean_string.starts_with("-") {
-                let style = span.style.bold();
-                span.style(style)
-            } else {
-                span
-            };
-            line.spans.push(span);
+        if self.kind == DiffLineKind::Divider {
+            line.push_span(Span::styled("~", Style::default().fg(Color::Magenta)));
+            return Ok(Text::from(line));
+        }
+
+        match self.format {
+            DiffFormat::ColorWords => {
+                if self.kind != DiffLineKind::NoNewline {
+                    line.extend(self.line_num_spans());
+                }
+            }
+            DiffFormat::Git => {
+                let marker = match self.kind {
+                    DiffLineKind::Added => "+",
+                    DiffLineKind::Removed => "-",
+                    DiffLineKind::NoNewline => "\\ ",
+                    DiffLineKind::Context | DiffLineKind::Divider => " ",
+                };
+                line.push_span(Span::styled(marker, self.content_style()));
+            }
+        }
+        line.push_span(Span::styled(self.content.clone(), self.content_style()));
+
+        Ok(Text::from(line))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos);
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        Vec::new()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        Vec::new()
+    }
+
+    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+struct SideBySideRow {
+    old_line: Option<DiffHunkLine>,
+    new_line: Option<DiffHunkLine>,
+    // Indices of the lines in the hunk, to keep the selection when switching views
+    line_idxs: Vec<usize>,
+    graph_indent: String,
+    width: u16,
+    flat_log_idx: usize,
+}
+
+impl SideBySideRow {
+    const COLUMN_SEPARATOR: &str = " │ ";
+
+    fn new(
+        old_line: Option<DiffHunkLine>,
+        new_line: Option<DiffHunkLine>,
+        line_idxs: Vec<usize>,
+        graph_indent: String,
+        width: u16,
+    ) -> Self {
+        Self {
+            old_line,
+            new_line,
+            line_idxs,
+            graph_indent,
+            width,
+            flat_log_idx: 0,
+        }
+    }
+
+    // Context lines are shown on both sides, but are only one line of the diff
+    fn diff_hunk_lines(&self) -> Vec<&DiffHunkLine> {
+        match (&self.old_line, &self.new_line) {
+            (Some(old_line), Some(new_line))
+                if old_line.kind == new_line.kind
+                    && !matches!(old_line.kind, DiffLineKind::Added | DiffLineKind::Removed) =>
+            {
+                vec![old_line]
+            }
+            (old_line, new_line) => old_line.iter().chain(new_line).collect(),
+        }
+    }
+
+    // Renders one side of the row, truncated or padded to exactly the column width
+    fn render_column(
+        line: Option<&DiffHunkLine>,
+        line_num: Option<u32>,
+        column_width: usize,
+    ) -> Vec<Span<'static>> {
+        let Some(line) = line else {
+            return vec![Span::raw(" ".repeat(column_width))];
+        };
+
+        let line_num_width = line.line_num_width;
+        let gutter = match line_num {
+            None => " ".repeat(line_num_width),
+            Some(line_num) => format!("{line_num:>line_num_width$}"),
+        };
+        let marker = match line.kind {
+            DiffLineKind::Added => "+",
+            DiffLineKind::Removed => "-",
+            _ => " ",
+        };
+
+        let content_width = column_width.saturating_sub(line_num_width + 2);
+        let content = line.content.replace('\t', "    ");
+        // Measured by display width, so wide characters don't overflow the column
+        let mut content: String = if Span::raw(content.as_str()).width() > content_width {
+            let mut truncated = String::new();
+            let mut truncated_width = 0;
+            for c in content.chars() {
+                let c_width = Span::raw(c.to_string()).width();
+                if truncated_width + c_width > content_width.saturating_sub(1) {
+                    break;
+                }
+                truncated.push(c);
+                truncated_width += c_width;
+            }
+            truncated.push('…');
+            truncated
+        } else {
+            content
+        };
+        let padding = content_width.saturating_sub(Span::raw(content.as_str()).width());
+        content.push_str(&" ".repeat(padding));
+
+        vec![
+            Span::styled(gutter, Style::default().fg(Color::DarkGray)),
+            Span::raw(" "),
+            Span::styled(marker, line.content_style()),
+            Span::styled(content, line.content_style()),
+        ]
+    }
+}
+
+impl LogTreeNode for SideBySideRow {
+    fn render(&self) -> Result<Text<'static>> {
+        if let Some(old_line) = &self.old_line
+            && old_line.kind == DiffLineKind::Divider
+        {
+            return old_line.render();
         }
 
+        let mut line = Line::from(vec![Span::raw(self.graph_indent.clone()), Span::raw("  ")]);
+
+        let indent_width = line.width();
+        let column_width = (self.width as usize)
+            .saturating_sub(indent_width + Span::raw(Self::COLUMN_SEPARATOR).width())
+            / 2;
+
+        line.extend(Self::render_column(
+            self.old_line.as_ref(),
+            self.old_line.as_ref().and_then(|l| l.old_line_num),
+            column_width,
+        ));
+        line.push_span(Span::styled(
+            Self::COLUMN_SEPARATOR,
+            Style::default().fg(Color::DarkGray),
+        ));
+        line.extend(Self::render_column(
+            self.new_line.as_ref(),
+            self.new_line.as_ref().and_then(|l| l.new_line_num),
+            column_width,
+        ));
+
         Ok(Text::from(line))
     }
 
@@ -915,12 +2749,58 @@ impl LogTreeNode for DiffHunkLine {
         Vec::new()
     }
 
+    fn children_mut(&mut self) -> Vec<&mut dyn LogTreeNode> {
+        Vec::new()
+    }
+
     fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
         Ok(())
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 fn fold_symbol(unfolded: bool) -> Span<'static> {
     let symbol = if unfolded { "▾" } else { "▸" };
     Span::styled(symbol, Style::default().fg(Color::DarkGray))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_by_side_columns_fit_wide_characters() {
+        let line = |kind, content: &str| {
+            DiffHunkLine::new(
+                kind,
+                content.to_string(),
+                Some(1),
+                Some(1),
+                "│ ".to_string(),
+            )
+        };
+        let row = SideBySideRow::new(
+            Some(line(DiffLineKind::Removed, "你好世界你好世界")),
+            Some(line(DiffLineKind::Added, "🙂🙂")),
+            vec![0, 1],
+            "│ ".to_string(),
+            31,
+        );
+
+        let text = row.render().unwrap();
+        assert_eq!(text.width(), 31);
+        let rendered: String = text.lines[0]
+            .spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect();
+        assert_eq!(rendered, "│   1 -你好世界… │ 1 +🙂🙂     ");
+    }
+}
@@ -1,14 +1,28 @@
+mod absorb;
 mod ansi;
 mod cli;
+mod clipboard;
+mod command_log;
 mod command_tree;
+mod editor;
+mod executor;
+#[cfg(test)]
+mod fixture_executor;
+mod headless;
 mod jj_commands;
 mod log_tree;
 mod model;
+mod pager;
+mod repo_watcher;
 mod terminal;
+mod text_area;
 mod update;
 mod view;
 
-use crate::model::{Model, State};
+use crate::command_log::CommandLog;
+use crate::executor::{JjExecutor, ProcessExecutor, RecordingExecutor};
+use crate::headless::Headless;
+use crate::model::{GlobalArgs, Model, State};
 use crate::update::update;
 use crate::view::view;
 
@@ -16,6 +30,7 @@ use anyhow::Result;
 use clap::Parser;
 use cli::Args;
 use jj_commands::JjCommand;
+use std::rc::Rc;
 use terminal::Term;
 
 fn main() {
@@ -27,12 +42,36 @@ fn main() {
 
 fn _main() -> Result<()> {
     let args = Args::parse();
-    let repository = JjCommand::ensure_valid_repo(&args.repository)?;
-    let model = Model::new(repository, args.revisions)?;
+    let executor: Rc<dyn JjExecutor> = match args.record_fixtures {
+        None => Rc::new(ProcessExecutor),
+        Some(fixture_path) => Rc::new(RecordingExecutor::new(fixture_path)),
+    };
+    let repository = JjCommand::ensure_valid_repo(&args.repository, executor.as_ref())?;
+    let command_log = CommandLog::new(args.log_internal_commands);
+    let global_args = GlobalArgs::new(repository, executor, command_log);
+    let mut model = Model::new(
+        global_args,
+        args.revisions,
+        args.no_confirm,
+        args.external_pager,
+    )?;
+
+    if let Some(script) = args.headless {
+        let (width, height) = args.headless_size;
+        let mut headless = Headless::new(model, width, height)?;
+        headless.run_script(&script)?;
+        println!("{}", headless.screen());
+        return Ok(());
+    }
+
+    if !args.no_auto_refresh {
+        model.watch_repo();
+    }
 
     let terminal = terminal::init_terminal()?;
     let result = main_loop(model, terminal);
     terminal::relinquish_terminal()?;
+    editor::remove_read_only_copies();
 
     result
 }
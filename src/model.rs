@@ -1,20 +1,42 @@
 use crate::{
-    command_tree::{CommandTree, CommandTreeNode, display_error_lines},
-    jj_commands::{JjCommand, JjCommandError},
-    log_tree::{DIFF_HUNK_LINE_IDX, JjLog, TreePosition, get_parent_tree_position},
+    absorb::{AbsorbDestination, preview_absorb},
+    ansi::strip_ansi,
+    cli::ConfirmCommand,
+    clipboard,
+    command_log::CommandLog,
+    command_tree::{CommandAction, CommandTree, CommandTreeNode, display_error_lines},
+    editor,
+    executor::JjExecutor,
+    jj_commands::{DiffFormat, DiffOptions, DiffRevisions, JjCommand, JjCommandError, Placement},
+    log_tree::{Commit, CommitOrText, JjLog, TreePosition, get_parent_tree_position},
+    pager::Pager,
+    repo_watcher::RepoWatcher,
     terminal::Term,
-    update::Message,
+    text_area::TextArea,
+    update::{CommandTarget, Message},
 };
 use ansi_to_tui::IntoText;
 use anyhow::Result;
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    layout::Rect,
-    text::{Line, Text},
+    layout::{Position, Rect},
+    style::{Color, Style},
+    text::{Line, Span, Text},
     widgets::ListState,
 };
+use std::{
+    ops::RangeInclusive,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 const LOG_LIST_SCROLL_PADDING: usize = 0;
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+const DESCRIPTION_SUMMARY_GUIDE: usize = 50;
+const DESCRIPTION_BODY_GUIDE: usize = 72;
+// Lines of the file shown above a hunk when previewing it
+const PREVIEW_HUNK_CONTEXT: u16 = 5;
 
 #[derive(Default, Debug, PartialEq, Eq)]
 pub enum State {
@@ -27,6 +49,130 @@ pub enum State {
 pub struct GlobalArgs {
     pub repository: String,
     pub ignore_immutable: bool,
+    pub diff_options: DiffOptions,
+    pub command_log: CommandLog,
+    pub executor: Rc<dyn JjExecutor>,
+}
+
+impl GlobalArgs {
+    pub fn new(repository: String, executor: Rc<dyn JjExecutor>, command_log: CommandLog) -> Self {
+        Self {
+            repository,
+            ignore_immutable: false,
+            diff_options: DiffOptions::default(),
+            command_log,
+            executor,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DescriptionTarget {
+    Describe { change_id: String },
+    Commit,
+}
+
+#[derive(Debug)]
+pub struct DescriptionEditor {
+    pub target: DescriptionTarget,
+    pub text_area: TextArea,
+}
+
+#[derive(Debug)]
+enum PromptAction {
+    // Only the first line is edited, the rest of the description is kept as it was
+    Description {
+        target: DescriptionTarget,
+        rest: String,
+    },
+    RangeDiff {
+        revset: String,
+    },
+    Duplicate {
+        change_id: String,
+        placement: Placement,
+    },
+    Revert {
+        change_id: String,
+        placement: Placement,
+    },
+    Next {
+        edit: bool,
+    },
+    Prev {
+        edit: bool,
+    },
+}
+
+#[derive(Debug)]
+pub struct Prompt {
+    pub label: String,
+    pub text_area: TextArea,
+    action: PromptAction,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Focus {
+    #[default]
+    Log,
+    Preview,
+    InfoPanel,
+}
+
+#[derive(Debug)]
+pub struct Preview {
+    pub text: Text<'static>,
+    pub scroll: u16,
+    pub layout: Rect,
+    // Position of the node the text was loaded for, None if it needs reloading
+    tree_pos: Option<TreePosition>,
+}
+
+#[derive(Debug, Default)]
+pub struct InfoPanel {
+    pub scroll: u16,
+    pub maximised: bool,
+    pub layout: Rect,
+    entries: InfoPanelEntries,
+}
+
+// What clicking a line of the info panel does
+#[derive(Debug, Default)]
+enum InfoPanelEntries {
+    #[default]
+    None,
+    // Help for the command keys pressed so far, where clicking an entry presses its key
+    Help(Vec<KeyCode>),
+    // The keys of the command each line runs
+    Menu(Vec<Option<Vec<KeyCode>>>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum YankTarget {
+    ChangeId,
+    CommitId,
+    FilePath,
+    Description,
+    Hunk,
+    FilePatch,
+}
+
+impl YankTarget {
+    fn describe(self) -> &'static str {
+        match self {
+            YankTarget::ChangeId => "change id",
+            YankTarget::CommitId => "commit id",
+            YankTarget::FilePath => "file path",
+            YankTarget::Description => "description",
+            YankTarget::Hunk => "hunk",
+            YankTarget::FilePatch => "file patch",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Confirmation {
+    cmd: JjCommand,
 }
 
 #[derive(Debug)]
@@ -42,8 +188,32 @@ pub struct Model {
     pub log_list_state: ListState,
     log_list_tree_positions: Vec<TreePosition>,
     pub log_list_layout: Rect,
+    log_list_width: u16,
     pub log_list_scroll_padding: usize,
     pub info_list: Option<Text<'static>>,
+    pub info_panel: InfoPanel,
+    // Kept after the info panel is cleared so it can be brought back
+    last_command_output: Option<Text<'static>>,
+    pub focus: Focus,
+    pub description_editor: Option<DescriptionEditor>,
+    pub prompt: Option<Prompt>,
+    pub confirmation: Option<Confirmation>,
+    pub preview: Option<Preview>,
+    pub pager: Option<Pager>,
+    external_pager: bool,
+    // Change marked as one end of a range, or the old side of a comparison
+    mark: Option<String>,
+    // Only set when auto refresh is on
+    repo_watcher: Option<RepoWatcher>,
+    // Changes from outside majjit that haven't been reloaded yet
+    repo_changed: bool,
+    // Without a real terminal, commands that hand it over to jj or an editor are refused
+    headless: bool,
+    // For telling double clicks apart, with the node that was clicked
+    last_click: Option<(Instant, usize)>,
+    // Start and end of the diff lines selected by dragging
+    line_range: Option<(usize, usize)>,
+    no_confirm: Vec<ConfirmCommand>,
 }
 
 #[derive(Debug)]
@@ -53,7 +223,12 @@ enum ScrollDirection {
 }
 
 impl Model {
-    pub fn new(repository: String, revset: String) -> Result<Self> {
+    pub fn new(
+        global_args: GlobalArgs,
+        revset: String,
+        no_confirm: Vec<ConfirmCommand>,
+        external_pager: bool,
+    ) -> Result<Self> {
         let mut model = Self {
             state: State::default(),
             command_tree: CommandTree::new(),
@@ -64,12 +239,26 @@ impl Model {
             log_list_state: ListState::default(),
             log_list_tree_positions: Vec::new(),
             log_list_layout: Rect::ZERO,
+            log_list_width: 0,
             log_list_scroll_padding: LOG_LIST_SCROLL_PADDING,
             info_list: None,
-            global_args: GlobalArgs {
-                repository,
-                ignore_immutable: false,
-            },
+            info_panel: InfoPanel::default(),
+            last_command_output: None,
+            focus: Focus::default(),
+            description_editor: None,
+            prompt: None,
+            confirmation: None,
+            preview: None,
+            pager: None,
+            external_pager,
+            mark: None,
+            repo_watcher: None,
+            repo_changed: false,
+            headless: false,
+            last_click: None,
+            line_range: None,
+            no_confirm,
+            global_args,
             revset,
         };
 
@@ -92,20 +281,122 @@ impl Model {
     }
 
     pub fn sync(&mut self) -> Result<()> {
-        self.jj_log.load_log_tree(&self.global_args, &self.revset)?;
+        let closed = self.jj_log.load_log_tree(&self.global_args, &self.revset)?;
+        self.show_closed_comparisons(closed);
+        self.mark_repo_seen();
         self.sync_log_list()?;
         self.reset_log_list_selection()?;
         Ok(())
     }
 
+    // Unlike sync, keeps folds and the selection
+    fn reload(&mut self) -> Result<()> {
+        let view_state = self.jj_log.view_state(&self.get_selected_tree_position());
+        let closed = self.jj_log.load_log_tree(&self.global_args, &self.revset)?;
+        self.show_closed_comparisons(closed);
+        self.mark_repo_seen();
+        let mut tree_pos = self
+            .jj_log
+            .restore_view_state(&self.global_args, &view_state)?;
+        self.sync_log_list()?;
+
+        // Fall back to the closest ancestor of the selected node that's still there
+        while !tree_pos.is_empty() {
+            if let Some(idx) = self
+                .log_list_tree_positions
+                .iter()
+                .position(|pos| *pos == tree_pos)
+            {
+                self.log_select(idx);
+                return Ok(());
+            }
+            tree_pos.pop();
+        }
+        self.log_select(0);
+        self.select_current_working_copy();
+        Ok(())
+    }
+
+    fn show_closed_comparisons(&mut self, closed: Vec<String>) {
+        if closed.is_empty() {
+            return;
+        }
+        let lines: Vec<Line> = closed
+            .into_iter()
+            .map(|reason| Line::styled(reason, Style::default().fg(Color::Red)))
+            .collect();
+        self.show_info(Text::from(lines));
+    }
+
+    pub fn set_headless(&mut self) {
+        self.headless = true;
+    }
+
+    fn interactive_term(&mut self, term: Term) -> Option<Term> {
+        if self.headless {
+            self.show_info(Text::from(Span::styled(
+                "This command needs a terminal, so it can't run headless",
+                Style::default().fg(Color::Red),
+            )));
+            return None;
+        }
+        Some(term)
+    }
+
+    pub fn watch_repo(&mut self) {
+        let snapshot_cmd = JjCommand::snapshot(self.global_args.clone());
+        self.repo_watcher = RepoWatcher::new(
+            &self.global_args.repository,
+            snapshot_cmd.global_flags_and_args(),
+        );
+    }
+
+    fn mark_repo_seen(&mut self) {
+        self.repo_changed = false;
+        if let Some(repo_watcher) = &mut self.repo_watcher {
+            repo_watcher.mark_seen();
+        }
+    }
+
+    pub fn check_repo_changes(&mut self) -> Result<()> {
+        let Some(repo_watcher) = &mut self.repo_watcher else {
+            return Ok(());
+        };
+        let (changed, snapshots) = repo_watcher.check();
+        self.repo_changed |= changed;
+
+        // Failures, like another jj holding the working copy lock, are left to the next snapshot,
+        // but always logged
+        let command_log = &self.global_args.command_log;
+        for entry in snapshots {
+            if entry.exit_code != Some(0) || command_log.record_internal {
+                command_log.record(entry);
+            }
+        }
+
+        // What an open prompt or confirmation shows would go stale, so wait for it to close
+        let is_busy = self.queued_jj_command.is_some()
+            || self.prompt.is_some()
+            || self.confirmation.is_some()
+            || self.description_editor.is_some();
+        if self.repo_changed && !is_busy {
+            self.reload()?;
+        }
+        Ok(())
+    }
+
     fn sync_log_list(&mut self) -> Result<()> {
         (self.log_list, self.log_list_tree_positions) = self.jj_log.flatten_log()?;
+        self.line_range = None;
+        if let Some(preview) = &mut self.preview {
+            preview.tree_pos = None;
+        }
         Ok(())
     }
 
     pub fn refresh(&mut self) -> Result<()> {
-        self.sync()?;
-        self.info_list = Some(Text::from("Refreshed"));
+        self.reload()?;
+        self.show_info(Text::from("Refreshed"));
         Ok(())
     }
 
@@ -113,6 +404,94 @@ impl Model {
         self.global_args.ignore_immutable = !self.global_args.ignore_immutable;
     }
 
+    pub fn toggle_diff_format(&mut self) -> Result<()> {
+        let diff_options = &mut self.global_args.diff_options;
+        diff_options.format = Some(match diff_options.format.unwrap_or_default() {
+            DiffFormat::ColorWords => DiffFormat::Git,
+            DiffFormat::Git => DiffFormat::ColorWords,
+        });
+        self.reload_diffs()
+    }
+
+    pub fn increase_diff_context(&mut self) -> Result<()> {
+        let diff_options = &mut self.global_args.diff_options;
+        let context = diff_options.context.unwrap_or(DiffOptions::DEFAULT_CONTEXT);
+        diff_options.context = Some(context + 1);
+        self.reload_diffs()
+    }
+
+    pub fn decrease_diff_context(&mut self) -> Result<()> {
+        let diff_options = &mut self.global_args.diff_options;
+        let context = diff_options.context.unwrap_or(DiffOptions::DEFAULT_CONTEXT);
+        diff_options.context = Some(context.saturating_sub(1));
+        self.reload_diffs()
+    }
+
+    pub fn toggle_ignore_all_space(&mut self) -> Result<()> {
+        let diff_options = &mut self.global_args.diff_options;
+        diff_options.ignore_all_space = !diff_options.ignore_all_space;
+        self.reload_diffs()
+    }
+
+    pub fn toggle_ignore_space_change(&mut self) -> Result<()> {
+        let diff_options = &mut self.global_args.diff_options;
+        diff_options.ignore_space_change = !diff_options.ignore_space_change;
+        self.reload_diffs()
+    }
+
+    pub fn reset_diff_options(&mut self) -> Result<()> {
+        self.global_args.diff_options = DiffOptions::default();
+        self.reload_diffs()
+    }
+
+    pub fn toggle_side_by_side(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let tree_pos = self
+            .jj_log
+            .toggle_side_by_side(&tree_pos, self.log_list_layout.width)?;
+        self.sync_log_list()?;
+        self.select_tree_position(tree_pos);
+        Ok(())
+    }
+
+    // Side-by-side diffs are laid out for a specific width, so redo them if the log area changed
+    pub fn handle_resize(&mut self) -> Result<()> {
+        let width = self.log_list_layout.width;
+        if width == self.log_list_width {
+            return Ok(());
+        }
+        self.log_list_width = width;
+        self.jj_log.resize(width);
+        self.sync_log_list()
+    }
+
+    fn reload_diffs(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        self.jj_log.reload_diffs(&self.global_args)?;
+        self.sync_log_list()?;
+        self.select_tree_position(tree_pos);
+        Ok(())
+    }
+
+    // Selects the node at the given position, or its closest ancestor if it no longer exists
+    fn select_tree_position(&mut self, tree_pos: TreePosition) {
+        let mut tree_pos = tree_pos;
+        loop {
+            if let Some(idx) = self
+                .log_list_tree_positions
+                .iter()
+                .position(|pos| *pos == tree_pos)
+            {
+                self.log_select(idx);
+                return;
+            }
+            match get_parent_tree_position(&tree_pos) {
+                None => return,
+                Some(parent_pos) => tree_pos = parent_pos,
+            }
+        }
+    }
+
     fn log_offset(&self) -> usize {
         self.log_list_state.offset()
     }
@@ -179,7 +558,7 @@ impl Model {
 
     fn select_next_sibling_node(&mut self, tree_pos: TreePosition) -> Result<()> {
         let mut tree_pos = tree_pos;
-        if tree_pos.len() == DIFF_HUNK_LINE_IDX + 1 {
+        if self.jj_log.is_diff_hunk_line(&tree_pos) {
             tree_pos = get_parent_tree_position(&tree_pos).unwrap();
         }
         let idx = tree_pos[tree_pos.len() - 1];
@@ -213,7 +592,7 @@ impl Model {
     }
 
     fn select_prev_sibling_node(&mut self, tree_pos: TreePosition) -> Result<()> {
-        if tree_pos.len() == DIFF_HUNK_LINE_IDX + 1 {
+        if self.jj_log.is_diff_hunk_line(&tree_pos) {
             let parent_pos = get_parent_tree_position(&tree_pos).unwrap();
             let parent_node_idx = self.jj_log.get_tree_node(&parent_pos)?.flat_log_idx();
             self.log_select(parent_node_idx);
@@ -255,11 +634,274 @@ impl Model {
 
     pub fn clear(&mut self) {
         self.info_list = None;
+        self.line_range = None;
+        self.info_panel.scroll = 0;
+        self.info_panel.maximised = false;
+        if self.focus == Focus::InfoPanel {
+            self.focus = Focus::Log;
+        }
         self.command_keys.clear();
     }
 
+    fn show_info(&mut self, text: Text<'static>) {
+        self.info_list = Some(text);
+        self.info_panel.scroll = 0;
+        self.info_panel.entries = InfoPanelEntries::None;
+    }
+
+    // Moves focus to the next visible pane out of the log, preview and info panel
+    pub fn cycle_focus(&mut self) {
+        let mut panes = vec![Focus::Log];
+        if self.preview.is_some() {
+            panes.push(Focus::Preview);
+        }
+        if self.info_list.is_some() {
+            panes.push(Focus::InfoPanel);
+        }
+        let current_idx = panes
+            .iter()
+            .position(|pane| *pane == self.focus)
+            .unwrap_or(0);
+        self.focus = panes[(current_idx + 1) % panes.len()];
+    }
+
+    pub fn focus_log(&mut self) {
+        self.focus = Focus::Log;
+    }
+
+    pub fn toggle_info_panel_maximised(&mut self) {
+        if self.info_list.is_none() {
+            let Some(output) = self.last_command_output.clone() else {
+                return;
+            };
+            self.show_info(output);
+            self.info_panel.maximised = true;
+            return;
+        }
+        self.info_panel.maximised = !self.info_panel.maximised;
+    }
+
+    pub fn handle_info_panel_key(&mut self, key: KeyEvent) -> Option<Message> {
+        match key.code {
+            KeyCode::Char('q') => Some(Message::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::Quit)
+            }
+            KeyCode::Down | KeyCode::Char('j') => Some(Message::ScrollInfoPanelDown),
+            KeyCode::Up | KeyCode::Char('k') => Some(Message::ScrollInfoPanelUp),
+            KeyCode::PageDown => Some(Message::ScrollInfoPanelDownPage),
+            KeyCode::PageUp => Some(Message::ScrollInfoPanelUpPage),
+            KeyCode::Char('z') => Some(Message::ToggleInfoPanelMaximised),
+            KeyCode::Char('o') => Some(Message::CycleFocus),
+            KeyCode::Esc => Some(Message::FocusLog),
+            _ => None,
+        }
+    }
+
+    pub fn scroll_info_panel(&mut self, lines: i32) {
+        let Some(info_list) = &self.info_list else {
+            return;
+        };
+        let visible_lines = self.info_panel.layout.height.saturating_sub(1);
+        let max_scroll = u16::try_from(info_list.lines.len())
+            .unwrap_or(u16::MAX)
+            .saturating_sub(visible_lines);
+        self.info_panel.scroll = self
+            .info_panel
+            .scroll
+            .saturating_add_signed(lines.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+            .min(max_scroll);
+    }
+
+    pub fn scroll_info_panel_page(&mut self, direction: i32) {
+        let page = self.info_panel.layout.height.saturating_sub(2).max(1) as i32;
+        self.scroll_info_panel(direction * page);
+    }
+
+    pub fn toggle_preview(&mut self) {
+        if self.focus == Focus::Preview {
+            self.focus = Focus::Log;
+        }
+        self.preview = match self.preview {
+            Some(_) => None,
+            None => Some(Preview {
+                text: Text::default(),
+                scroll: 0,
+                layout: Rect::ZERO,
+                tree_pos: None,
+            }),
+        };
+    }
+
+    pub fn handle_preview_key(&mut self, key: KeyEvent) -> Option<Message> {
+        match key.code {
+            KeyCode::Char('q') => Some(Message::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::Quit)
+            }
+            KeyCode::Down | KeyCode::Char('j') => Some(Message::ScrollPreviewDown),
+            KeyCode::Up | KeyCode::Char('k') => Some(Message::ScrollPreviewUp),
+            KeyCode::PageDown => Some(Message::ScrollPreviewDownPage),
+            KeyCode::PageUp => Some(Message::ScrollPreviewUpPage),
+            KeyCode::Char('o') => Some(Message::CycleFocus),
+            KeyCode::Esc => Some(Message::FocusLog),
+            KeyCode::Char('p') => Some(Message::TogglePreview),
+            _ => None,
+        }
+    }
+
+    pub fn scroll_preview(&mut self, lines: i32) {
+        let Some(preview) = &mut self.preview else {
+            return;
+        };
+        let max_scroll = u16::try_from(preview.text.lines.len())
+            .unwrap_or(u16::MAX)
+            .saturating_sub(1);
+        preview.scroll = preview
+            .scroll
+            .saturating_add_signed(lines.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+            .min(max_scroll);
+    }
+
+    pub fn scroll_preview_page(&mut self, direction: i32) {
+        let Some(preview) = &self.preview else {
+            return;
+        };
+        let page = preview.layout.height.saturating_sub(2).max(1) as i32;
+        self.scroll_preview(direction * page);
+    }
+
+    // Reloads the preview if the selection moved to a different node since it was last loaded
+    pub fn sync_preview(&mut self) -> Result<()> {
+        let Some(preview) = &self.preview else {
+            return Ok(());
+        };
+        if self.log_list_tree_positions.is_empty() {
+            return Ok(());
+        }
+
+        let mut tree_pos = self.get_selected_tree_position();
+        if self.jj_log.is_diff_hunk_line(&tree_pos) {
+            tree_pos = get_parent_tree_position(&tree_pos).unwrap();
+        }
+        if preview.tree_pos.as_ref() == Some(&tree_pos) {
+            return Ok(());
+        }
+
+        let (text, scroll) = self.load_preview(&tree_pos)?;
+        if let Some(preview) = &mut self.preview {
+            preview.text = text;
+            preview.scroll = scroll;
+            preview.tree_pos = Some(tree_pos);
+        }
+        Ok(())
+    }
+
+    fn load_preview(&self, tree_pos: &TreePosition) -> Result<(Text<'static>, u16)> {
+        let global_args = self.global_args.clone();
+
+        if let Some(file_diff) = self.jj_log.get_tree_file_diff(tree_pos) {
+            if let Some(diff_hunk) = self.jj_log.get_tree_diff_hunk(tree_pos) {
+                let cmd = JjCommand::file_show(
+                    file_diff.diff_revisions.to_revision(),
+                    &file_diff.path,
+                    global_args,
+                );
+                let (start, len) = diff_hunk.new_range();
+                let text = match run_preview_command(cmd)? {
+                    Ok(output) => file_context_text(&output, start, len),
+                    Err(text) => text,
+                };
+                let scroll = (start as u16).saturating_sub(PREVIEW_HUNK_CONTEXT + 1);
+                return Ok((text, scroll));
+            }
+
+            let cmd = JjCommand::diff_file_formatted(
+                &file_diff.diff_revisions,
+                &file_diff.path,
+                global_args,
+            );
+            return Ok((preview_command_text(cmd)?, 0));
+        }
+
+        if let Some(file) = self.jj_log.get_tree_file_tree_file(tree_pos) {
+            let cmd = JjCommand::file_show(&file.change_id, &file.path, global_args);
+            let text = match run_preview_command(cmd)? {
+                Ok(output) => file_context_text(&output, 0, 0),
+                Err(text) => text,
+            };
+            return Ok((text, 0));
+        }
+
+        let cmd = if let Some(evolog_entry) = self.jj_log.get_tree_evolog_entry(tree_pos) {
+            JjCommand::log_detailed(&evolog_entry.commit_id, global_args)
+        } else if let Some(commit) = self.jj_log.get_tree_commit(tree_pos) {
+            JjCommand::log_detailed(&commit.change_id, global_args)
+        } else {
+            return Ok((Text::default(), 0));
+        };
+        Ok((preview_command_text(cmd)?, 0))
+    }
+
     pub fn show_help(&mut self) {
-        self.info_list = Some(self.command_tree.get_help());
+        self.show_info(self.command_tree.get_help());
+        self.info_panel.entries = InfoPanelEntries::Help(Vec::new());
+    }
+
+    // Lists the commands that act on the selected change or file, each run by clicking its line
+    pub fn show_context_menu(&mut self) {
+        let Some(change_id) = self.get_selected_change_id() else {
+            self.show_info(Text::from("No commands for the selected node"));
+            return;
+        };
+        let maybe_file_path = self.get_selected_file_path();
+        let title = match maybe_file_path {
+            Some(file_path) => format!(" Commands for {file_path} in {change_id}"),
+            None => format!(" Commands for {change_id}"),
+        };
+
+        let actions: Vec<CommandAction> = self
+            .command_tree
+            .get_actions()
+            .into_iter()
+            .filter(|action| match action.message.command_target() {
+                Some(CommandTarget::Change) => true,
+                Some(CommandTarget::File) => maybe_file_path.is_some(),
+                None => false,
+            })
+            .collect();
+        let keys_text = |action: &CommandAction| {
+            action
+                .key_codes
+                .iter()
+                .map(KeyCode::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let keys_width = actions
+            .iter()
+            .map(|action| keys_text(action).chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![Line::from(Span::styled(
+            title,
+            Style::default().fg(Color::Blue),
+        ))];
+        let mut entries = vec![None];
+        for action in actions {
+            lines.push(Line::from(vec![
+                Span::raw(" "),
+                Span::styled(
+                    format!("{:keys_width$}", keys_text(&action)),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::raw(format!("  {}: {}", action.group, action.help)),
+            ]));
+            entries.push(Some(action.key_codes));
+        }
+        self.show_info(Text::from(lines));
+        self.info_panel.entries = InfoPanelEntries::Menu(entries);
     }
 
     pub fn handle_command_key(&mut self, key_code: KeyCode) -> Option<Message> {
@@ -275,7 +917,8 @@ impl Model {
         };
         match node {
             CommandTreeNode::Children(children) => {
-                self.info_list = Some(children.get_help());
+                self.show_info(children.get_help());
+                self.info_panel.entries = InfoPanelEntries::Help(self.command_keys.clone());
                 None
             }
             CommandTreeNode::Action(message) => {
@@ -286,6 +929,10 @@ impl Model {
     }
 
     pub fn scroll_down_once(&mut self) {
+        if let Some(pager) = &mut self.pager {
+            pager.scroll_by(1);
+            return;
+        }
         if self.log_selected() <= self.log_offset() + self.log_list_scroll_padding {
             self.select_next_node();
         }
@@ -293,6 +940,10 @@ impl Model {
     }
 
     pub fn scroll_up_once(&mut self) {
+        if let Some(pager) = &mut self.pager {
+            pager.scroll_by(-1);
+            return;
+        }
         if self.log_offset() == 0 {
             return;
         }
@@ -338,25 +989,133 @@ impl Model {
         *self.log_list_state.offset_mut() = target_offset;
     }
 
-    pub fn handle_mouse_click(&mut self, row: u16, column: u16) {
-        let Rect {
-            x,
-            y,
-            width,
-            height,
-        } = self.log_list_layout;
+    // The pane under a position of the terminal, None for the header and prompt
+    pub fn pane_at(&self, row: u16, column: u16) -> Option<Focus> {
+        let position = Position::new(column, row);
+        if self.info_list.is_some()
+            && self.description_editor.is_none()
+            && self.info_panel.layout.contains(position)
+        {
+            return Some(Focus::InfoPanel);
+        }
+        if let Some(preview) = &self.preview
+            && preview.layout.contains(position)
+        {
+            return Some(Focus::Preview);
+        }
+        self.log_list_layout
+            .contains(position)
+            .then_some(Focus::Log)
+    }
+
+    // The node at a position of the log list, and which of its lines is there
+    fn log_list_hit(&self, row: u16, column: u16) -> Option<(usize, usize)> {
+        if self.pane_at(row, column) != Some(Focus::Log) {
+            return None;
+        }
+        let line_dist = (row - self.log_list_layout.y) as usize;
+        let node =
+            self.line_dist_to_dest_node(line_dist, self.log_offset(), &ScrollDirection::Down);
+        let lines_above: usize = self.log_list[self.log_offset()..node]
+            .iter()
+            .map(|text| text.lines.len())
+            .sum();
+        Some((node, line_dist - lines_above))
+    }
+
+    pub fn select_clicked_node(&mut self, row: u16, column: u16) -> Option<usize> {
+        let (node, _) = self.log_list_hit(row, column)?;
+        self.focus = Focus::Log;
+        self.log_select(node);
+        Some(node)
+    }
+
+    pub fn handle_mouse_click(&mut self, row: u16, column: u16) -> Option<Message> {
+        if self.pager.is_some() {
+            return None;
+        }
+        match self.pane_at(row, column)? {
+            Focus::InfoPanel => self.click_info_panel(row, column),
+            Focus::Preview => {
+                self.focus = Focus::Preview;
+                None
+            }
+            Focus::Log => {
+                let (node, line_idx) = self.log_list_hit(row, column)?;
+                self.focus = Focus::Log;
+                self.log_select(node);
+                // Dragging from here selects a range of diff lines
+                self.line_range = Some((node, node));
+
+                let column = column - self.log_list_layout.x;
+                if self.fold_glyph_column(node, line_idx) == Some(column) {
+                    self.last_click = None;
+                    return Some(Message::ToggleLogListFold);
+                }
+                let is_double_click = self.last_click.is_some_and(|(time, last_node)| {
+                    last_node == node && time.elapsed() < DOUBLE_CLICK_INTERVAL
+                });
+                self.last_click = (!is_double_click).then(|| (Instant::now(), node));
+                is_double_click.then_some(Message::Show)
+            }
+        }
+    }
+
+    // Where the ▸/▾ of a foldable node is drawn, if it's on this line
+    fn fold_glyph_column(&self, node: usize, line_idx: usize) -> Option<u16> {
+        let line = self.log_list[node].lines.get(line_idx)?.to_string();
+        let glyph_idx = line.find(['▸', '▾'])?;
+        Some(Line::raw(&line[..glyph_idx]).width() as u16)
+    }
+
+    fn click_info_panel(&mut self, row: u16, column: u16) -> Option<Message> {
+        self.focus = Focus::InfoPanel;
+        // The panel's first row is its border
+        let line = (row - self.info_panel.layout.y).checked_sub(1)? as usize
+            + self.info_panel.scroll as usize;
+        let column = (column - self.info_panel.layout.x) as usize;
+        let key_codes = match &self.info_panel.entries {
+            InfoPanelEntries::None => return None,
+            InfoPanelEntries::Help(command_keys) => {
+                let children = self.command_tree.get_children(command_keys)?;
+                let mut key_codes = command_keys.clone();
+                key_codes.push(children.get_help_key_at(line, column)?);
+                key_codes
+            }
+            InfoPanelEntries::Menu(entries) => entries.get(line)?.clone()?,
+        };
+
+        self.focus = Focus::Log;
+        let (last_key_code, key_codes) = key_codes.split_last()?;
+        self.command_keys = key_codes.to_vec();
+        self.handle_command_key(*last_key_code)
+    }
+
+    pub fn handle_mouse_drag(&mut self, row: u16, column: u16) {
+        let Some((anchor, _)) = self.line_range else {
+            return;
+        };
+        let Some((node, _)) = self.log_list_hit(row, column) else {
+            return;
+        };
 
-        // Check if inside log list
-        if row < y || row >= y + height || column < x || column >= x + width {
+        // Ranges don't extend past the hunk they started in
+        let anchor_pos = &self.log_list_tree_positions[anchor];
+        let node_pos = &self.log_list_tree_positions[node];
+        if !self.jj_log.is_diff_hunk_line(anchor_pos)
+            || !self.jj_log.is_diff_hunk_line(node_pos)
+            || get_parent_tree_position(anchor_pos) != get_parent_tree_position(node_pos)
+        {
             return;
         }
+        self.line_range = Some((anchor, node));
+        self.log_select(node);
+    }
 
-        let target_node = self.line_dist_to_dest_node(
-            row as usize - y as usize,
-            self.log_offset(),
-            &ScrollDirection::Down,
-        );
-        self.log_select(target_node);
+    // Indices into the log list of the diff lines selected by dragging
+    pub fn selected_line_range(&self) -> Option<RangeInclusive<usize>> {
+        let (anchor, end) = self.line_range?;
+        (anchor != end).then(|| anchor.min(end)..=anchor.max(end))
     }
 
     // Since some nodes contain multiple lines, we need a way to determine the destination node
@@ -392,44 +1151,709 @@ impl Model {
     }
 
     pub fn jj_show(&mut self, term: Term) -> Result<()> {
-        let Some(change_id) = self.get_selected_change_id() else {
-            return Ok(());
-        };
-        let maybe_file_path = self.get_selected_file_path();
-        let cmd = JjCommand::show(change_id, maybe_file_path, self.global_args.clone(), term);
-        self.run_jj_command_nosync(cmd)
-    }
+        let tree_pos = self.get_selected_tree_position();
+        // File contents always go to the internal pager, since jj doesn't page them
+        if let Some(file) = self.jj_log.get_tree_file_tree_file(&tree_pos) {
+            let cmd = JjCommand::file_show(&file.change_id, &file.path, self.global_args.clone());
+            return self.open_file_pager(cmd);
+        }
 
-    pub fn jj_describe(&mut self, term: Term) -> Result<()> {
-        let Some(change_id) = self.get_selected_change_id() else {
-            return Ok(());
+        let maybe_term = if self.external_pager {
+            let Some(term) = self.interactive_term(term) else {
+                return Ok(());
+            };
+            Some(term)
+        } else {
+            None
         };
-        let cmd = JjCommand::describe(change_id, self.global_args.clone(), term);
-        self.queue_jj_command(cmd)
-    }
 
-    pub fn jj_new(&mut self) -> Result<()> {
-        let Some(change_id) = self.get_selected_change_id() else {
+        let cmd = if let Some(file_diff) = self.jj_log.get_tree_file_diff(&tree_pos) {
+            JjCommand::show_file_diff(
+                &file_diff.diff_revisions,
+                &file_diff.path,
+                self.global_args.clone(),
+                maybe_term,
+            )
+        } else if let Some(evolog_entry) = self.jj_log.get_tree_evolog_entry(&tree_pos) {
+            JjCommand::show_diff_from(
+                &evolog_entry.commit_id,
+                &evolog_entry.change_id,
+                None,
+                self.global_args.clone(),
+                maybe_term,
+            )
+        } else if let Some(change_id) = self.get_selected_change_id() {
+            JjCommand::show(change_id, self.global_args.clone(), maybe_term)
+        } else {
             return Ok(());
         };
-        let cmd = JjCommand::new(change_id, self.global_args.clone());
-        self.queue_jj_command(cmd)
+
+        if self.external_pager {
+            return self.run_jj_command_nosync(cmd);
+        }
+        self.open_pager(cmd)
     }
 
-    pub fn jj_new_before(&mut self) -> Result<()> {
-        let Some(change_id) = self.get_selected_change_id() else {
-            return Ok(());
-        };
-        let cmd = JjCommand::new_before(change_id, self.global_args.clone());
-        self.queue_jj_command(cmd)
+    fn open_pager(&mut self, cmd: JjCommand) -> Result<()> {
+        let title = cmd.command_line();
+        if let Some(output) = self.run_jj_command_output(cmd)? {
+            self.pager = Some(Pager::new(title, output.into_text()?));
+        }
+        Ok(())
     }
 
-    pub fn jj_abandon(&mut self) -> Result<()> {
-        let Some(change_id) = self.get_selected_change_id() else {
-            return Ok(());
-        };
-        let cmd = JjCommand::abandon(change_id, self.global_args.clone());
-        self.queue_jj_command(cmd)
+    fn open_file_pager(&mut self, cmd: JjCommand) -> Result<()> {
+        let title = cmd.command_line();
+        if let Some(output) = self.run_jj_command_output(cmd)? {
+            let text = file_context_text(&output, 0, 0);
+            self.pager = Some(Pager::new(title, text));
+        }
+        Ok(())
+    }
+
+    pub fn show_command_log(&mut self) -> Result<()> {
+        let text = self.global_args.command_log.to_text()?;
+        self.pager = Some(Pager::new("Command log".to_string(), text).scrolled_to_bottom());
+        Ok(())
+    }
+
+    pub fn handle_pager_key(&mut self, key: KeyEvent) -> Option<Message> {
+        self.pager.as_mut()?.handle_key(key)
+    }
+
+    pub fn close_pager(&mut self) {
+        self.pager = None;
+    }
+
+    // Selects the change the pager's cursor line comes from, adding it to the revset if needed
+    pub fn jump_to_pager_target(&mut self) -> Result<()> {
+        let Some(change_id) = self
+            .pager
+            .as_ref()
+            .and_then(Pager::selected_target)
+            .map(str::to_string)
+        else {
+            return Ok(());
+        };
+        self.pager = None;
+
+        if self.jj_log.find_commit(&change_id).is_none() {
+            self.revset = format!("({}) | {change_id}", self.revset);
+            self.sync()?;
+            self.show_info(Text::from(format!("Revset widened to {}", self.revset)));
+        }
+        if let Some(commit) = self.jj_log.find_commit(&change_id) {
+            self.log_select(commit.flat_log_idx);
+        }
+        Ok(())
+    }
+
+    pub fn jj_describe(&mut self, term: Term) -> Result<()> {
+        let Some(term) = self.interactive_term(term) else {
+            return Ok(());
+        };
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let cmd = JjCommand::describe(change_id, self.global_args.clone(), term);
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_describe_inline(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let target = DescriptionTarget::Describe {
+            change_id: change_id.to_string(),
+        };
+        self.open_description_editor(target)
+    }
+
+    pub fn jj_commit_inline(&mut self) -> Result<()> {
+        self.open_description_editor(DescriptionTarget::Commit)
+    }
+
+    fn get_description(&mut self, target: &DescriptionTarget) -> Result<Option<String>> {
+        let change_id = match target {
+            DescriptionTarget::Describe { change_id } => change_id,
+            DescriptionTarget::Commit => "@",
+        };
+        let cmd = JjCommand::description(change_id, self.global_args.clone());
+        let maybe_output = self.run_jj_command_output(cmd)?;
+        Ok(maybe_output.map(|output| strip_ansi(&output)))
+    }
+
+    fn open_description_editor(&mut self, target: DescriptionTarget) -> Result<()> {
+        let Some(description) = self.get_description(&target)? else {
+            return Ok(());
+        };
+
+        self.clear();
+        self.description_editor = Some(DescriptionEditor {
+            target,
+            text_area: TextArea::new(description.trim_end())
+                .with_line_length_guides(DESCRIPTION_SUMMARY_GUIDE, DESCRIPTION_BODY_GUIDE),
+        });
+        Ok(())
+    }
+
+    pub fn handle_description_editor_key(&mut self, key: KeyEvent) -> Option<Message> {
+        let editor = self.description_editor.as_mut()?;
+        match key.code {
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::SaveDescription)
+            }
+            KeyCode::Esc => Some(Message::CancelDescription),
+            _ => {
+                editor.text_area.handle_key(key);
+                None
+            }
+        }
+    }
+
+    pub fn save_description(&mut self) -> Result<()> {
+        let Some(editor) = self.description_editor.take() else {
+            return Ok(());
+        };
+        let message = editor.text_area.text();
+        let cmd = Self::description_command(&editor.target, &message, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    fn description_command(
+        target: &DescriptionTarget,
+        message: &str,
+        global_args: GlobalArgs,
+    ) -> JjCommand {
+        match target {
+            DescriptionTarget::Describe { change_id } => {
+                JjCommand::describe_message(change_id, message, global_args)
+            }
+            DescriptionTarget::Commit => JjCommand::commit_message(message, global_args),
+        }
+    }
+
+    pub fn cancel_description(&mut self) {
+        self.description_editor = None;
+    }
+
+    pub fn jj_describe_quick(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let label = format!("Describe {change_id}: ");
+        let target = DescriptionTarget::Describe {
+            change_id: change_id.to_string(),
+        };
+        self.open_description_prompt(label, target)
+    }
+
+    pub fn jj_commit_quick(&mut self) -> Result<()> {
+        self.open_description_prompt("Commit @: ".to_string(), DescriptionTarget::Commit)
+    }
+
+    fn open_description_prompt(&mut self, label: String, target: DescriptionTarget) -> Result<()> {
+        let Some(description) = self.get_description(&target)? else {
+            return Ok(());
+        };
+        let description = description.trim_end();
+        let (first_line, rest) = match description.split_once('\n') {
+            Some((first_line, body)) => (first_line, format!("\n{body}")),
+            None => (description, String::new()),
+        };
+        let action = PromptAction::Description { target, rest };
+        self.open_prompt(label, Some(first_line.to_string()), action);
+        Ok(())
+    }
+
+    fn open_prompt(&mut self, label: String, text: Option<String>, action: PromptAction) {
+        self.clear();
+        self.prompt = Some(Prompt {
+            label,
+            text_area: TextArea::new(text.as_deref().unwrap_or_default()),
+            action,
+        });
+    }
+
+    pub fn handle_prompt_key(&mut self, key: KeyEvent) -> Option<Message> {
+        let prompt = self.prompt.as_mut()?;
+        match key.code {
+            KeyCode::Enter => Some(Message::SubmitPrompt),
+            KeyCode::Esc => Some(Message::CancelPrompt),
+            _ => {
+                prompt.text_area.handle_key(key);
+                None
+            }
+        }
+    }
+
+    pub fn submit_prompt(&mut self) -> Result<()> {
+        let Some(prompt) = self.prompt.take() else {
+            return Ok(());
+        };
+        let text = prompt.text_area.text();
+        match prompt.action {
+            PromptAction::Description { target, rest } => {
+                let message = format!("{text}{rest}");
+                let cmd = Self::description_command(&target, &message, self.global_args.clone());
+                self.queue_jj_command(cmd)
+            }
+            PromptAction::RangeDiff { revset } => self.range_diff(text, revset),
+            PromptAction::Duplicate {
+                change_id,
+                placement,
+            } => {
+                let placement = Some((placement, text.as_str()));
+                let cmd = JjCommand::duplicate(&change_id, placement, self.global_args.clone());
+                self.queue_jj_command(cmd)
+            }
+            PromptAction::Next { edit } => {
+                let cmd = JjCommand::next(&text, edit, self.global_args.clone());
+                self.queue_jj_command(cmd)
+            }
+            PromptAction::Prev { edit } => {
+                let cmd = JjCommand::prev(&text, edit, self.global_args.clone());
+                self.queue_jj_command(cmd)
+            }
+            PromptAction::Revert {
+                change_id,
+                placement,
+            } => {
+                let cmd = JjCommand::revert(&change_id, placement, &text, self.global_args.clone());
+                self.queue_jj_command(cmd)
+            }
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        self.prompt = None;
+    }
+
+    pub fn mark_change(&mut self) {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return;
+        };
+        let change_id = change_id.to_string();
+        self.show_info(
+            Line::from(vec![
+                Span::raw("Marked "),
+                Span::styled(change_id.clone(), Style::default().fg(Color::Magenta)),
+                Span::raw(", select another change to compare with or use as a range"),
+            ])
+            .into(),
+        );
+        self.mark = Some(change_id);
+    }
+
+    pub fn compare_marked(&mut self) -> Result<()> {
+        let Some(to) = self.get_selected_change_id().map(str::to_string) else {
+            return Ok(());
+        };
+        let Some(from) = self.mark.clone() else {
+            self.show_info(Text::from("No change marked, mark one with m first"));
+            return Ok(());
+        };
+        self.compare(from, to)
+    }
+
+    pub fn compare_trunk(&mut self) -> Result<()> {
+        self.compare("trunk()".to_string(), "@".to_string())
+    }
+
+    pub fn compare_remote(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id().map(str::to_string) else {
+            return Ok(());
+        };
+        let cmd = JjCommand::local_bookmark_names(&change_id, self.global_args.clone());
+        let Some(output) = self.run_jj_command_output(cmd)? else {
+            return Ok(());
+        };
+        let Some(bookmark) = strip_ansi(&output).lines().next().map(str::to_string) else {
+            self.show_info(Text::from("Selected change has no bookmark"));
+            return Ok(());
+        };
+
+        let cmd = JjCommand::tracked_remote_bookmarks(&bookmark, self.global_args.clone());
+        let Some(output) = self.run_jj_command_output(cmd)? else {
+            return Ok(());
+        };
+        let Some(remote_bookmark) = strip_ansi(&output).lines().next().map(str::to_string) else {
+            self.show_info(Text::from(format!(
+                "Bookmark {bookmark} isn't tracking a remote"
+            )));
+            return Ok(());
+        };
+        self.compare(remote_bookmark, change_id)
+    }
+
+    fn compare(&mut self, from: String, to: String) -> Result<()> {
+        // Check the revisions resolve first, so jj's error can be shown
+        let diff_revisions = DiffRevisions::FromTo {
+            from: from.clone(),
+            to: to.clone(),
+        };
+        let cmd = JjCommand::diff_summary(&diff_revisions, self.global_args.clone());
+        if self.run_jj_command_output(cmd)?.is_none() {
+            return Ok(());
+        }
+
+        self.jj_log.set_comparison(Some((from, to)));
+        self.sync()?;
+        self.log_select(0);
+        Ok(())
+    }
+
+    // Range-diff of the selected change's stack, from an operation the user enters to now
+    pub fn compare_operation(&mut self) {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return;
+        };
+        let revset = format!("trunk()..{change_id}");
+        let label = format!("Range-diff {revset} from operation: ");
+        // The operation before the current one
+        let text = Some("@-".to_string());
+        self.open_prompt(label, text, PromptAction::RangeDiff { revset });
+    }
+
+    fn range_diff(&mut self, old_operation: String, revset: String) -> Result<()> {
+        // Check the stack can be loaded at the operation first, so jj's error can be shown
+        let cmd = JjCommand::stack_changes(&revset, Some(&old_operation), self.global_args.clone());
+        if self.run_jj_command_output(cmd)?.is_none() {
+            return Ok(());
+        }
+
+        self.jj_log.set_range_diff(Some((old_operation, revset)));
+        self.sync()?;
+        let range_diff_idx = self
+            .jj_log
+            .log_tree
+            .iter()
+            .find(|item| matches!(item, CommitOrText::RangeDiff(_)))
+            .map(CommitOrText::flat_log_idx);
+        if let Some(range_diff_idx) = range_diff_idx {
+            self.log_select(range_diff_idx);
+        }
+        Ok(())
+    }
+
+    pub fn close_comparison(&mut self) -> Result<()> {
+        if !self.jj_log.has_comparisons() {
+            return Ok(());
+        }
+        self.jj_log.set_comparison(None);
+        self.jj_log.set_range_diff(None);
+        self.sync()
+    }
+
+    pub fn yank(&mut self, target: YankTarget) -> Result<()> {
+        let Some(text) = self.yank_text(target)? else {
+            return Ok(());
+        };
+        clipboard::copy(&text)?;
+        let num_lines = text.lines().count();
+        let summary = if num_lines > 1 {
+            format!("Copied {} ({num_lines} lines)", target.describe())
+        } else {
+            format!("Copied {}: {}", target.describe(), text.trim_end())
+        };
+        self.show_info(Text::from(summary));
+        Ok(())
+    }
+
+    // None if there's nothing to copy, after saying why in the info panel
+    fn yank_text(&mut self, target: YankTarget) -> Result<Option<String>> {
+        let tree_pos = self.get_selected_tree_position();
+        let maybe_text = match target {
+            YankTarget::ChangeId => self.get_selected_change_id().map(str::to_string),
+            YankTarget::CommitId => self
+                .jj_log
+                .get_tree_commit(&tree_pos)
+                .map(|commit| commit.commit_id.clone()),
+            YankTarget::FilePath => self
+                .get_selected_file_path()
+                .or_else(|| {
+                    self.jj_log
+                        .get_tree_file_tree_file(&tree_pos)
+                        .map(|file| file.path.as_str())
+                })
+                .map(str::to_string),
+            YankTarget::Description => match self.get_selected_change_id() {
+                None => None,
+                Some(change_id) => {
+                    let cmd = JjCommand::description(change_id, self.global_args.clone());
+                    let Some(output) = self.run_jj_command_output(cmd)? else {
+                        return Ok(None);
+                    };
+                    Some(output.trim_end().to_string()).filter(|text| !text.is_empty())
+                }
+            },
+            YankTarget::Hunk => match self.selected_line_range() {
+                // Lines selected by dragging are copied on their own, since they don't apply
+                Some(line_range) => {
+                    let tree_positions = &self.log_list_tree_positions[line_range];
+                    Some(self.jj_log.get_tree_diff_lines_patch(tree_positions))
+                        .filter(|patch| !patch.is_empty())
+                }
+                None => match self.jj_log.get_tree_diff_hunk(&tree_pos) {
+                    None => None,
+                    Some(diff_hunk) => {
+                        let hunk_patch = diff_hunk.patch_text();
+                        let Some(file_patch) = self.selected_file_patch()? else {
+                            return Ok(None);
+                        };
+                        // Keep the file header, so the hunk can be applied
+                        let header_len = file_patch.find("\n@@").map_or(0, |idx| idx + 1);
+                        Some(format!("{}{hunk_patch}", &file_patch[..header_len]))
+                    }
+                },
+            },
+            YankTarget::FilePatch => {
+                if self.jj_log.get_tree_file_diff(&tree_pos).is_none() {
+                    None
+                } else {
+                    let Some(file_patch) = self.selected_file_patch()? else {
+                        return Ok(None);
+                    };
+                    Some(file_patch)
+                }
+            }
+        };
+
+        if maybe_text.is_none() {
+            self.show_info(Text::from(format!("No {} to copy", target.describe())));
+        }
+        Ok(maybe_text)
+    }
+
+    // The git diff of the selected file, with the diff options in use
+    fn selected_file_patch(&mut self) -> Result<Option<String>> {
+        let tree_pos = self.get_selected_tree_position();
+        let Some(file_diff) = self.jj_log.get_tree_file_diff(&tree_pos) else {
+            return Ok(None);
+        };
+        let cmd = JjCommand::diff_file(
+            &file_diff.diff_revisions,
+            &file_diff.path,
+            self.global_args.clone(),
+        );
+        let maybe_output = self.run_jj_command_output(cmd)?;
+        Ok(maybe_output.map(|output| strip_ansi(&output)))
+    }
+
+    pub fn jj_new(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let cmd = JjCommand::new(change_id, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_new_before(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let cmd = JjCommand::new_before(change_id, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_duplicate(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let cmd = JjCommand::duplicate(change_id, None, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_duplicate_to(&mut self, placement: Placement) {
+        let Some(change_id) = self.get_selected_change_id().map(str::to_string) else {
+            return;
+        };
+        let label = format!("Duplicate {change_id} {}: ", placement.describe());
+        let action = PromptAction::Duplicate {
+            change_id,
+            placement,
+        };
+        self.open_prompt(label, Some("@".to_string()), action);
+    }
+
+    pub fn jj_revert(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let cmd = JjCommand::revert(change_id, Placement::Onto, "@", self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_revert_to(&mut self, placement: Placement) {
+        let Some(change_id) = self.get_selected_change_id().map(str::to_string) else {
+            return;
+        };
+        let label = format!("Revert {change_id} {}: ", placement.describe());
+        let action = PromptAction::Revert {
+            change_id,
+            placement,
+        };
+        self.open_prompt(label, Some("@".to_string()), action);
+    }
+
+    // Parallelizes the changes between the marked and selected ones, whichever is the ancestor
+    pub fn jj_parallelize(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return Ok(());
+        };
+        let Some(mark) = &self.mark else {
+            self.show_info(Text::from(
+                "No change marked, mark one end of the range with m first",
+            ));
+            return Ok(());
+        };
+        let revset = format!("({mark}::{change_id}) | ({change_id}::{mark})");
+        let cmd = JjCommand::parallelize(&revset, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    // The log is reloaded after these, which selects the new @
+    pub fn jj_next(&mut self, edit: bool) -> Result<()> {
+        let cmd = JjCommand::next("1", edit, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_next_by(&mut self, edit: bool) {
+        let label = if edit {
+            "Edit child"
+        } else {
+            "New change on child"
+        };
+        let label = format!("{label} this many generations down: ");
+        self.open_prompt(label, Some("2".to_string()), PromptAction::Next { edit });
+    }
+
+    pub fn jj_prev(&mut self, edit: bool) -> Result<()> {
+        let cmd = JjCommand::prev("1", edit, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_prev_by(&mut self, edit: bool) {
+        let label = if edit {
+            "Edit parent"
+        } else {
+            "New change on parent"
+        };
+        let label = format!("{label} this many generations up: ");
+        self.open_prompt(label, Some("2".to_string()), PromptAction::Prev { edit });
+    }
+
+    pub fn jj_abandon(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let Some(commit) = self.jj_log.get_tree_commit(&tree_pos) else {
+            return Ok(());
+        };
+        let cmd = JjCommand::abandon(&commit.change_id, self.global_args.clone());
+        if self.no_confirm.contains(&ConfirmCommand::Abandon) {
+            return self.queue_jj_command(cmd);
+        }
+
+        let change_line = change_summary_line("Abandon", commit);
+        let revset = format!("descendants({0}) ~ {0}", commit.change_id);
+        let descendants_cmd = JjCommand::change_ids(&revset, self.global_args.clone());
+        let Some(num_descendants) = self.count_output_lines(descendants_cmd)? else {
+            return Ok(());
+        };
+
+        let lines = vec![
+            change_line,
+            Line::raw(format!(
+                "{num_descendants} descendant(s) will be rebased onto its parents"
+            )),
+        ];
+        self.request_confirmation(cmd, lines);
+        Ok(())
+    }
+
+    // Shows where each hunk would go before asking to run absorb
+    pub fn jj_absorb(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let Some(commit) = self.jj_log.get_tree_commit(&tree_pos) else {
+            return Ok(());
+        };
+        let maybe_file_path = self.get_selected_file_path();
+
+        let cmd = JjCommand::absorb(&commit.change_id, maybe_file_path, self.global_args.clone());
+        let mut lines = vec![change_summary_line("Absorb", commit)];
+        // jj absorbs whole files, so a selected hunk can't be absorbed on its own
+        let hunk_selected = self.jj_log.get_tree_diff_hunk(&tree_pos).is_some();
+        if let Some(file_path) = maybe_file_path.filter(|_| hunk_selected) {
+            lines.push(Line::styled(
+                format!("Absorbs all of {file_path}, not just the selected hunk"),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+
+        let hunks = match preview_absorb(&self.global_args, &commit.change_id, maybe_file_path) {
+            Ok(Some(hunks)) => hunks,
+            Ok(None) => {
+                lines.push(Line::styled(
+                    "Can't preview absorbing a merge",
+                    Style::default().fg(Color::Yellow),
+                ));
+                self.request_confirmation(cmd, lines);
+                return Ok(());
+            }
+            Err(JjCommandError::Failed { stderr }) => {
+                self.show_info(stderr.into_text()?);
+                return Ok(());
+            }
+            Err(JjCommandError::Other { err }) => return Err(err),
+        };
+
+        for hunk in &hunks {
+            let mut line = Line::from(vec![
+                Span::raw("  "),
+                Span::raw(format!("{}:{}", hunk.path, hunk.new_start)),
+                Span::styled(
+                    format!(" (-{} +{})", hunk.num_removed, hunk.num_added),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(" → "),
+            ]);
+            let reason = match &hunk.destination {
+                AbsorbDestination::Change(change_id) => {
+                    line.push_span(Span::styled(
+                        change_id.chars().take(8).collect::<String>(),
+                        Style::default().fg(Color::Magenta),
+                    ));
+                    if let Some(destination) = self.jj_log.find_commit(change_id) {
+                        let description = destination
+                            .description_first_line
+                            .clone()
+                            .unwrap_or_else(|| "(no description set)".to_string());
+                        line.push_span(Span::raw(format!(" {description}")));
+                    }
+                    lines.push(line);
+                    continue;
+                }
+                AbsorbDestination::OnlyAddsLines => "only adds lines",
+                AbsorbDestination::SeveralChanges => "lines from several changes",
+                AbsorbDestination::Immutable => "lines from an immutable change",
+            };
+            line.push_span(Span::styled(
+                format!("stays ({reason})"),
+                Style::default().fg(Color::DarkGray),
+            ));
+            lines.push(line);
+        }
+
+        let absorbs_anything = hunks
+            .iter()
+            .any(|hunk| matches!(hunk.destination, AbsorbDestination::Change(_)));
+        if !absorbs_anything {
+            lines.push(Line::raw("Nothing to absorb"));
+            self.show_info(Text::from(lines));
+            return Ok(());
+        }
+
+        self.request_confirmation(cmd, lines);
+        Ok(())
     }
 
     pub fn jj_undo(&mut self) -> Result<()> {
@@ -438,6 +1862,9 @@ impl Model {
     }
 
     pub fn jj_commit(&mut self, term: Term) -> Result<()> {
+        let Some(term) = self.interactive_term(term) else {
+            return Ok(());
+        };
         let cmd = JjCommand::commit(self.global_args.clone(), term);
         self.queue_jj_command(cmd)
     }
@@ -449,8 +1876,45 @@ impl Model {
         };
         let maybe_file_path = self.get_selected_file_path();
 
-        let cmd = JjCommand::restore(&commit.change_id, maybe_file_path, self.global_args.clone());
-        self.queue_jj_command(cmd)
+        let (cmd, diff_revisions, action) = match self.jj_log.get_tree_evolog_entry(&tree_pos) {
+            Some(evolog_entry) => (
+                JjCommand::restore_from(
+                    &evolog_entry.commit_id,
+                    &evolog_entry.change_id,
+                    maybe_file_path,
+                    self.global_args.clone(),
+                ),
+                DiffRevisions::FromTo {
+                    from: evolog_entry.commit_id.clone(),
+                    to: evolog_entry.change_id.clone(),
+                },
+                format!("Restore to version {} of", evolog_entry.commit_id),
+            ),
+            None => (
+                JjCommand::restore(&commit.change_id, maybe_file_path, self.global_args.clone()),
+                DiffRevisions::Change(commit.change_id.clone()),
+                "Restore".to_string(),
+            ),
+        };
+        if self.no_confirm.contains(&ConfirmCommand::Restore) {
+            return self.queue_jj_command(cmd);
+        }
+
+        let change_line = change_summary_line(&action, commit);
+        let files_line = match maybe_file_path {
+            Some(file_path) => format!("Changes to {file_path} will be discarded"),
+            None => {
+                let summary_cmd =
+                    JjCommand::diff_summary(&diff_revisions, self.global_args.clone());
+                let Some(num_files) = self.count_output_lines(summary_cmd)? else {
+                    return Ok(());
+                };
+                format!("Changes to {num_files} file(s) will be discarded")
+            }
+        };
+
+        self.request_confirmation(cmd, vec![change_line, Line::raw(files_line)]);
+        Ok(())
     }
 
     pub fn jj_squash(&mut self, term: Term) -> Result<()> {
@@ -458,20 +1922,26 @@ impl Model {
         let Some(commit) = self.jj_log.get_tree_commit(&tree_pos) else {
             return Ok(());
         };
-        let maybe_file_path = self.get_selected_file_path();
-
-        let cmd = if commit.description_first_line.is_none() {
-            JjCommand::squash_noninteractive(
-                &commit.change_id,
-                maybe_file_path,
+        let change_id = commit.change_id.clone();
+        let has_description = commit.description_first_line.is_some();
+        let maybe_file_path = self.get_selected_file_path().map(str::to_string);
+
+        let cmd = if has_description {
+            // jj asks for the combined description
+            let Some(term) = self.interactive_term(term) else {
+                return Ok(());
+            };
+            JjCommand::squash_interactive(
+                &change_id,
+                maybe_file_path.as_deref(),
                 self.global_args.clone(),
+                term,
             )
         } else {
-            JjCommand::squash_interactive(
-                &commit.change_id,
-                maybe_file_path,
+            JjCommand::squash_noninteractive(
+                &change_id,
+                maybe_file_path.as_deref(),
                 self.global_args.clone(),
-                term,
             )
         };
         self.queue_jj_command(cmd)
@@ -485,6 +1955,80 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
+    pub fn jj_file_annotate(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let (revision, path) = if let Some(file) = self.jj_log.get_tree_file_tree_file(&tree_pos) {
+            (file.change_id.clone(), file.path.clone())
+        } else if let Some(file_diff) = self.jj_log.get_tree_file_diff(&tree_pos) {
+            (
+                file_diff.diff_revisions.to_revision().to_string(),
+                file_diff.path.clone(),
+            )
+        } else {
+            return Ok(());
+        };
+
+        let cmd = JjCommand::file_annotate(&revision, &path, self.global_args.clone());
+        let title = cmd.command_line();
+        if let Some(output) = self.run_jj_command_output(cmd)? {
+            let (text, change_ids) = annotate_text(&strip_ansi(&output));
+            self.pager = Some(Pager::new(title, text).with_line_targets(change_ids));
+        }
+        Ok(())
+    }
+
+    // Opens the working copy's file when the diff is of @, otherwise a read-only copy of the file
+    // at the diff's revision
+    pub fn open_in_editor(&mut self, term: Term) -> Result<()> {
+        let Some(term) = self.interactive_term(term) else {
+            return Ok(());
+        };
+        let tree_pos = self.get_selected_tree_position();
+        let Some(file_diff) = self.jj_log.get_tree_file_diff(&tree_pos) else {
+            return Ok(());
+        };
+        let revision = file_diff.diff_revisions.to_revision().to_string();
+        let file_path = file_diff.path.clone();
+        // Removed lines aren't in the new file, so they go to the start of their hunk
+        let line_num = self
+            .jj_log
+            .get_tree_diff_line_new_line_num(&tree_pos)
+            .or_else(|| {
+                let diff_hunk = self.jj_log.get_tree_diff_hunk(&tree_pos)?;
+                Some(diff_hunk.new_range().0)
+            })
+            .unwrap_or(1)
+            .max(1);
+        let is_working_copy = revision == "@"
+            || self
+                .jj_log
+                .get_current_commit()
+                .is_some_and(|commit| commit.change_id == revision);
+
+        let path = if is_working_copy {
+            Path::new(&self.global_args.repository).join(&file_path)
+        } else {
+            let cmd = JjCommand::file_show(&revision, &file_path, self.global_args.clone());
+            let Some(output) = self.run_jj_command_output(cmd)? else {
+                return Ok(());
+            };
+            match editor::write_read_only_copy(&revision, &file_path, &output) {
+                Ok(path) => path,
+                Err(err) => {
+                    self.show_info(Text::from(format!("Cannot copy {file_path}: {err}")));
+                    return Ok(());
+                }
+            }
+        };
+
+        let result = editor::open_at_line(&path, line_num, term);
+        self.reload()?;
+        if let Err(err) = result {
+            self.show_info(Text::from(err.to_string()));
+        }
+        Ok(())
+    }
+
     pub fn jj_fetch(&mut self) -> Result<()> {
         let cmd = JjCommand::fetch(self.global_args.clone());
         self.queue_jj_command(cmd)
@@ -492,7 +2036,19 @@ impl Model {
 
     pub fn jj_push(&mut self) -> Result<()> {
         let cmd = JjCommand::push(self.global_args.clone());
-        self.queue_jj_command(cmd)
+        if self.no_confirm.contains(&ConfirmCommand::Push) {
+            return self.queue_jj_command(cmd);
+        }
+
+        let dry_run_cmd = JjCommand::push_dry_run(self.global_args.clone());
+        let Some(output) = self.run_jj_command_output(dry_run_cmd)? else {
+            return Ok(());
+        };
+
+        let mut lines = vec![Line::raw("Push to git remote:")];
+        lines.extend(output.trim_end().into_text()?.lines);
+        self.request_confirmation(cmd, lines);
+        Ok(())
     }
 
     pub fn jj_bookmark_set_master(&mut self) -> Result<()> {
@@ -503,10 +2059,43 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
+    fn request_confirmation(&mut self, cmd: JjCommand, summary_lines: Vec<Line<'static>>) {
+        let mut lines = cmd.to_lines();
+        lines.extend(summary_lines);
+        lines.push(Line::raw(""));
+        lines.push(Line::from(vec![
+            Span::raw("Press "),
+            Span::styled("y", Style::default().fg(Color::Green)),
+            Span::raw(" to confirm, any other key to cancel"),
+        ]));
+        self.clear();
+        self.show_info(Text::from(lines));
+        self.confirmation = Some(Confirmation { cmd });
+    }
+
+    pub fn handle_confirmation_key(&self, key: KeyEvent) -> Option<Message> {
+        match key.code {
+            KeyCode::Char('y') => Some(Message::Confirm),
+            _ => Some(Message::CancelConfirmation),
+        }
+    }
+
+    pub fn confirm(&mut self) -> Result<()> {
+        let Some(confirmation) = self.confirmation.take() else {
+            return Ok(());
+        };
+        self.queue_jj_command(confirmation.cmd)
+    }
+
+    pub fn cancel_confirmation(&mut self) {
+        self.confirmation = None;
+        self.show_info(Text::from("Cancelled"));
+    }
+
     fn queue_jj_command(&mut self, cmd: JjCommand) -> Result<()> {
         let mut lines = cmd.to_lines();
         lines.push(Line::raw("Running..."));
-        self.info_list = Some(Text::from(lines));
+        self.show_info(Text::from(lines));
         self.queued_jj_command = Some(cmd);
         Ok(())
     }
@@ -519,6 +2108,22 @@ impl Model {
         Ok(())
     }
 
+    // Runs a command whose output is needed by majjit itself, only displaying it on failure
+    fn run_jj_command_output(&mut self, mut cmd: JjCommand) -> Result<Option<String>> {
+        match cmd.run() {
+            Ok(output) => Ok(Some(output)),
+            Err(err) => {
+                self.handle_jj_command_result(&cmd, Err(err), false)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn count_output_lines(&mut self, cmd: JjCommand) -> Result<Option<usize>> {
+        let maybe_output = self.run_jj_command_output(cmd)?;
+        Ok(maybe_output.map(|output| output.trim().lines().count()))
+    }
+
     fn run_jj_command_nosync(&mut self, mut cmd: JjCommand) -> Result<()> {
         let result = cmd.run();
         self.handle_jj_command_result(&cmd, result, false)
@@ -538,7 +2143,12 @@ impl Model {
             Ok(output) => {
                 lines.extend(output.into_text()?.lines);
                 if sync_on_success {
-                    self.sync()?
+                    self.sync()?;
+                    // Anything shown while syncing, like why a comparison closed, goes below
+                    if let Some(sync_info) = self.info_list.take() {
+                        lines.push(Line::raw(""));
+                        lines.extend(sync_info.lines);
+                    }
                 }
             }
             Err(err) => match err {
@@ -549,7 +2159,655 @@ impl Model {
             },
         };
 
-        self.info_list = Some(Text::from(lines));
+        let output = Text::from(lines);
+        self.last_command_output = Some(output.clone());
+        self.show_info(output);
         Ok(())
     }
 }
+
+// Failures are shown in the preview itself rather than the info list, since the preview
+// reloads on every selection change
+fn run_preview_command(mut cmd: JjCommand) -> Result<Result<String, Text<'static>>> {
+    match cmd.run() {
+        Ok(output) => Ok(Ok(output)),
+        Err(JjCommandError::Failed { stderr }) => Ok(Err(stderr.into_text()?)),
+        Err(JjCommandError::Other { err }) => Err(err),
+    }
+}
+
+fn preview_command_text(cmd: JjCommand) -> Result<Text<'static>> {
+    match run_preview_command(cmd)? {
+        Ok(output) => Ok(output.into_text()?),
+        Err(text) => Ok(text),
+    }
+}
+
+// Numbers the lines of a file and highlights the ones covered by a hunk, if any
+fn file_context_text(content: &str, start: u32, len: u32) -> Text<'static> {
+    let num_lines = content.lines().count();
+    let line_num_width = num_lines.to_string().len();
+    let hunk_lines = start..start + len;
+
+    content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            let line_num = idx as u32 + 1;
+            let (marker, line_num_style) = if hunk_lines.contains(&line_num) {
+                ("▌", Style::default().fg(Color::Green))
+            } else {
+                (" ", Style::default().fg(Color::DarkGray))
+            };
+            Line::from(vec![
+                Span::styled(format!("{line_num:>line_num_width$}"), line_num_style),
+                Span::styled(marker, Style::default().fg(Color::Green)),
+                Span::raw(line.replace('\t', "    ")),
+            ])
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+// Puts the change id and author in a gutter on the first line of each run of lines from the same
+// change, and returns the change id of every line
+fn annotate_text(output: &str) -> (Text<'static>, Vec<Option<String>>) {
+    let lines: Vec<(&str, &str, &str)> = output
+        .lines()
+        .map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            (
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+            )
+        })
+        .collect();
+    let author_width = lines
+        .iter()
+        .map(|(_, author, _)| author.chars().count())
+        .max()
+        .unwrap_or(0);
+    let line_num_width = lines.len().to_string().len();
+
+    let mut text_lines = Vec::new();
+    let mut prev_change_id = "";
+    for (idx, (change_id, author, content)) in lines.iter().enumerate() {
+        let gutter = if *change_id == prev_change_id {
+            vec![Span::raw(" ".repeat(change_id.len() + 1 + author_width))]
+        } else {
+            vec![
+                Span::styled(change_id.to_string(), Style::default().fg(Color::Magenta)),
+                Span::raw(" "),
+                Span::styled(
+                    format!("{author:author_width$}"),
+                    Style::default().fg(Color::Yellow),
+                ),
+            ]
+        };
+        prev_change_id = change_id;
+
+        let mut line = Line::from(gutter);
+        line.push_span(Span::styled(
+            format!(" {:>line_num_width$} ", idx + 1),
+            Style::default().fg(Color::DarkGray),
+        ));
+        line.push_span(Span::raw(content.replace('\t', "    ")));
+        text_lines.push(line);
+    }
+
+    let change_ids = lines
+        .iter()
+        .map(|(change_id, _, _)| Some(change_id.to_string()))
+        .collect();
+    (Text::from(text_lines), change_ids)
+}
+
+fn change_summary_line(action: &str, commit: &Commit) -> Line<'static> {
+    let description = commit
+        .description_first_line
+        .clone()
+        .unwrap_or_else(|| "(no description set)".to_string());
+    Line::from(vec![
+        Span::raw(format!("{action} change ")),
+        Span::styled(
+            commit.change_id.clone(),
+            Style::default().fg(Color::Magenta),
+        ),
+        Span::raw(": "),
+        Span::raw(description),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_executor::FixtureExecutor;
+    use std::path::Path;
+
+    fn load_model(fixture_file: &str) -> (Model, Rc<FixtureExecutor>) {
+        let fixture_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(fixture_file);
+        let executor = Rc::new(FixtureExecutor::from_file(&fixture_path).unwrap());
+        let global_args = GlobalArgs::new(
+            "/repo".to_string(),
+            executor.clone(),
+            CommandLog::new(false),
+        );
+        let model = Model::new(global_args, "all()".to_string(), Vec::new(), false).unwrap();
+        (model, executor)
+    }
+
+    fn plain_text(text: &Text) -> String {
+        text.lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn selected_text(model: &Model) -> String {
+        plain_text(&model.log_list[model.log_selected()])
+    }
+
+    #[test]
+    fn working_copy_starts_selected_and_unfolded() {
+        let (model, _) = load_model("two_commits.txt");
+
+        assert_eq!(
+            model.log_list_tree_positions,
+            vec![
+                vec![0],
+                vec![0, 0],
+                vec![0, 1],
+                vec![0, 2],
+                vec![0, 3],
+                vec![1],
+                vec![2]
+            ]
+        );
+        assert_eq!(model.log_selected(), 0);
+        assert!(selected_text(&model).contains("qpvuntsm"));
+        assert!(plain_text(&model.log_list[1]).contains("hello.txt"));
+        assert!(plain_text(&model.log_list[3]).contains("Evolution log"));
+        assert!(plain_text(&model.log_list[4]).contains("File tree"));
+    }
+
+    #[test]
+    fn evolog_compares_each_version_with_its_own_predecessor() {
+        let (mut model, executor) = load_model("two_commits.txt");
+
+        model.log_select(3);
+        model.toggle_current_fold().unwrap();
+        let lines: Vec<String> = model.log_list.iter().map(plain_text).collect();
+        assert!(lines[4].contains("qpvuntsm test@example.com"));
+        assert!(lines[5].contains("ysrnknol hidden"));
+        assert!(lines[5].contains("Mention qpvuntsm in 7a7b7c7d"));
+        assert!(lines[6].contains("qpvuntsm hidden test@example.com 2025-01-02"));
+        assert!(lines[7].contains("qpvuntsm hidden test@example.com 2025-01-01"));
+        assert!(lines[7].contains("-- operation 4444dddd4444"));
+
+        // The squashed-in version is listed next, but the change was rewritten from 7a7b7c7d
+        model.log_select(4);
+        model.toggle_current_fold().unwrap();
+        assert!(executor.calls().iter().any(|call| {
+            call.ends_with(&[
+                "interdiff".to_string(),
+                "--from".to_string(),
+                "7a7b7c7d7e7f7a7b7c7d7e7f7a7b7c7d7e7f7a7b".to_string(),
+                "--to".to_string(),
+                "a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4e5f6a1b2".to_string(),
+                "--summary".to_string(),
+            ])
+        }));
+        assert!(plain_text(&model.log_list[5]).contains("hello.txt"));
+    }
+
+    #[test]
+    fn navigation_moves_between_siblings_and_parents() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.select_next_node();
+        assert!(selected_text(&model).contains("hello.txt"));
+        model.select_current_next_sibling_node().unwrap();
+        assert!(selected_text(&model).contains("src/main.rs"));
+        model.select_parent_node().unwrap();
+        assert!(selected_text(&model).contains("qpvuntsm"));
+        model.select_current_next_sibling_node().unwrap();
+        assert!(selected_text(&model).contains("rlvkpnrz"));
+        model.select_current_prev_sibling_node().unwrap();
+        assert!(selected_text(&model).contains("qpvuntsm"));
+    }
+
+    #[test]
+    fn folding_a_file_diff_loads_its_hunks_once() {
+        let (mut model, executor) = load_model("two_commits.txt");
+
+        model.select_next_node();
+        model.toggle_current_fold().unwrap();
+        let lines: Vec<String> = model.log_list.iter().map(plain_text).collect();
+        assert_eq!(lines.len(), 13);
+        assert!(lines[2].contains("@@ -1,3 +1,3 @@"));
+        assert!(lines[4].contains("hello"));
+        assert!(lines[5].contains("hello world"));
+        assert!(lines[7].contains('~'));
+        assert!(lines[8].contains("src/main.rs"));
+
+        let num_calls = executor.calls().len();
+        model.toggle_current_fold().unwrap();
+        model.toggle_current_fold().unwrap();
+        assert_eq!(model.log_list.len(), 13);
+        assert_eq!(executor.calls().len(), num_calls);
+    }
+
+    #[test]
+    fn refresh_keeps_folds_and_selection() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.select_next_node();
+        model.toggle_current_fold().unwrap();
+        model.select_next_node();
+        assert!(selected_text(&model).contains("@@ -1,3 +1,3 @@"));
+
+        // The next log in the fixture has a new working copy above the selected change
+        model.refresh().unwrap();
+        assert!(plain_text(&model.log_list[0]).contains("wqnwkozp"));
+        assert!(selected_text(&model).contains("@@ -1,3 +1,3 @@"));
+        assert_eq!(model.get_selected_tree_position(), vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn clicks_toggle_folds_show_and_open_context_menus() {
+        let (mut model, _) = load_model("two_commits.txt");
+        model.log_list_layout = Rect::new(0, 2, 80, 30);
+
+        // The working copy's first line is "@ ▾ qpvuntsm ..."
+        assert_eq!(
+            model.handle_mouse_click(2, 2),
+            Some(Message::ToggleLogListFold)
+        );
+        assert_eq!(model.handle_mouse_click(4, 10), None);
+        assert!(selected_text(&model).contains("hello.txt"));
+        assert_eq!(model.handle_mouse_click(4, 10), Some(Message::Show));
+
+        model.show_context_menu();
+        let menu = plain_text(model.info_list.as_ref().unwrap());
+        assert!(menu.starts_with(" Commands for hello.txt in qpvuntsm"));
+        assert!(menu.contains("File: Annotate selected file"));
+        assert!(!menu.contains("Git"));
+
+        // Clicking a line of the menu runs its command
+        model.info_panel.layout = Rect::new(0, 20, 80, 40);
+        let line = menu.lines().position(|line| line.contains("f a")).unwrap();
+        assert_eq!(
+            model.handle_mouse_click(21 + line as u16, 3),
+            Some(Message::FileAnnotate)
+        );
+    }
+
+    #[test]
+    fn dragging_selects_diff_lines_within_a_hunk() {
+        let (mut model, _) = load_model("two_commits.txt");
+        model.log_list_layout = Rect::new(0, 2, 80, 30);
+        model.select_next_node();
+        model.toggle_current_fold().unwrap();
+
+        // The hunk's lines are at indices 3 to 7, with its file's next sibling after them
+        model.handle_mouse_click(6, 10);
+        model.handle_mouse_drag(8, 10);
+        assert_eq!(model.selected_line_range(), Some(3..=5));
+        // Dragging out of the hunk keeps the range
+        model.handle_mouse_drag(11, 10);
+        assert_eq!(model.selected_line_range(), Some(3..=5));
+        model.clear();
+        assert_eq!(model.selected_line_range(), None);
+    }
+
+    #[test]
+    fn yanks_hunks_with_their_file_header() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        assert_eq!(
+            model.yank_text(YankTarget::ChangeId).unwrap().as_deref(),
+            Some("qpvuntsm")
+        );
+        assert_eq!(model.yank_text(YankTarget::Hunk).unwrap(), None);
+        assert!(plain_text(model.info_list.as_ref().unwrap()).contains("No hunk to copy"));
+
+        model.select_next_node();
+        model.toggle_current_fold().unwrap();
+        model.select_next_node();
+        let hunk = model.yank_text(YankTarget::Hunk).unwrap().unwrap();
+        assert!(hunk.starts_with("diff --git a/hello.txt b/hello.txt\n"));
+        assert!(hunk.contains("\n@@ -1,3 +1,3 @@\n first\n-hello\n+hello world\n last\n"));
+
+        // Side-by-side rows are copied as the lines they pair up
+        model.log_list_layout = Rect::new(0, 2, 80, 30);
+        model.toggle_side_by_side().unwrap();
+        assert!(plain_text(&model.log_list[4]).contains(" │ "));
+        model.handle_mouse_click(6, 10);
+        model.handle_mouse_drag(8, 10);
+        assert_eq!(
+            model.yank_text(YankTarget::Hunk).unwrap().as_deref(),
+            Some(" first\n-hello\n+hello world\n last\n")
+        );
+    }
+
+    #[test]
+    fn side_by_side_toggle_keeps_the_selected_line() {
+        let (mut model, _) = load_model("two_commits.txt");
+        model.log_list_layout = Rect::new(0, 2, 80, 30);
+        model.select_next_node();
+        model.toggle_current_fold().unwrap();
+
+        // The added line is shown next to the line it replaces
+        model.log_select(5);
+        assert!(selected_text(&model).ends_with("2: hello world"));
+        model.toggle_side_by_side().unwrap();
+        assert_eq!(model.log_selected(), 4);
+        assert!(selected_text(&model).contains("hello world"));
+
+        model.log_select(5);
+        model.toggle_side_by_side().unwrap();
+        assert_eq!(model.log_selected(), 6);
+        assert!(selected_text(&model).ends_with("last"));
+    }
+
+    #[test]
+    fn side_by_side_rows_open_at_their_new_line() {
+        let (mut model, _) = load_model("two_commits.txt");
+        model.log_list_layout = Rect::new(0, 2, 80, 30);
+        model.select_next_node();
+        model.toggle_current_fold().unwrap();
+        model.log_select(4);
+        model.toggle_side_by_side().unwrap();
+
+        let line_num = |model: &Model, idx: usize| {
+            let tree_pos = &model.log_list_tree_positions[idx];
+            model.jj_log.get_tree_diff_line_new_line_num(tree_pos)
+        };
+        assert!(plain_text(&model.log_list[4]).contains("hello world"));
+        assert_eq!(line_num(&model, 4), Some(2));
+        assert_eq!(line_num(&model, 5), Some(3));
+    }
+
+    #[test]
+    fn file_tree_lists_directories_first_and_shows_files() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.log_select(4);
+        model.toggle_current_fold().unwrap();
+        let lines: Vec<String> = model.log_list.iter().map(plain_text).collect();
+        assert!(lines[5].ends_with("▸ src/"));
+        assert!(lines[6].ends_with("  README.md"));
+        assert!(lines[7].ends_with("  hello.txt"));
+
+        model.log_select(5);
+        model.toggle_current_fold().unwrap();
+        let lines: Vec<String> = model.log_list.iter().map(plain_text).collect();
+        assert!(lines[6].ends_with("▸ util/"));
+        assert!(lines[7].ends_with("main.rs"));
+
+        model.log_select(7);
+        model
+            .jj_show(crate::terminal::detached_terminal().unwrap())
+            .unwrap();
+        let pager = model.pager.as_mut().unwrap();
+        let (text, _, _) = pager.render(10);
+        assert!(plain_text(&text).starts_with("1 fn main() {\n2     println!"));
+
+        // Tab on a file folds its directory
+        model.close_pager();
+        model.toggle_current_fold().unwrap();
+        assert!(selected_text(&model).ends_with("▸ src/"));
+    }
+
+    #[test]
+    fn file_tree_files_open_in_the_pager_when_headless() {
+        let (mut model, _) = load_model("two_commits.txt");
+        model.set_headless();
+        model.external_pager = true;
+
+        model.log_select(4);
+        model.toggle_current_fold().unwrap();
+        model.log_select(5);
+        model.toggle_current_fold().unwrap();
+        model.log_select(7);
+        model
+            .jj_show(crate::terminal::detached_terminal().unwrap())
+            .unwrap();
+        assert!(model.pager.is_some());
+    }
+
+    #[test]
+    fn annotate_jumps_to_the_change_of_a_line() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.select_next_node();
+        model.jj_file_annotate().unwrap();
+        let pager = model.pager.as_mut().unwrap();
+        let (text, _, _) = pager.render(10);
+        let lines: Vec<String> = text
+            .lines
+            .iter()
+            .map(|l| plain_text(&l.clone().into()))
+            .collect();
+        assert_eq!(lines[0], "rlvkpnrz Alice 1 first");
+        assert_eq!(lines[1], "qpvuntsm Bob   2 hello world");
+        assert_eq!(lines[2], "rlvkpnrz Alice 3 last");
+
+        pager.handle_key(KeyEvent::from(KeyCode::Char('j')));
+        pager.handle_key(KeyEvent::from(KeyCode::Char('j')));
+        let msg = pager.handle_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(msg, Some(Message::JumpToPagerTarget));
+        model.jump_to_pager_target().unwrap();
+        assert!(model.pager.is_none());
+        assert!(selected_text(&model).contains("rlvkpnrz"));
+        assert_eq!(model.revset, "all()");
+    }
+
+    #[test]
+    fn comparison_is_shown_above_the_log_until_closed() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.compare_marked().unwrap();
+        assert!(plain_text(model.info_list.as_ref().unwrap()).contains("No change marked"));
+
+        model.compare_trunk().unwrap();
+        assert_eq!(model.log_selected(), 0);
+        assert_eq!(selected_text(&model), "▾ Compare trunk() → @ (2 files)");
+        assert!(plain_text(&model.log_list[1]).contains("hello.txt"));
+        assert!(plain_text(&model.log_list[2]).contains("src/main.rs"));
+        assert!(model.jj_log.get_tree_commit(&vec![0]).is_none());
+
+        model.close_comparison().unwrap();
+        assert!(!plain_text(&model.log_list[0]).contains("Compare"));
+    }
+
+    #[test]
+    fn range_diff_pairs_changes_by_change_id() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.compare_operation();
+        assert_eq!(
+            model.prompt.as_ref().unwrap().label,
+            "Range-diff trunk()..qpvuntsm from operation: "
+        );
+        model.submit_prompt().unwrap();
+
+        let lines: Vec<String> = model.log_list.iter().map(plain_text).collect();
+        assert_eq!(
+            lines[0],
+            "▾ Range-diff trunk()..qpvuntsm at operation @- → now (3 changes)"
+        );
+        assert_eq!(lines[1], "  ▸ qpvuntsm changed   Add greeting");
+        assert_eq!(lines[2], "  ▸ mzvwutvl added     (no description set)");
+        assert_eq!(lines[3], "  ▸ ysrnknol removed   Dropped change");
+        assert_eq!(model.log_selected(), 0);
+
+        model.log_select(1);
+        model.toggle_current_fold().unwrap();
+        assert!(plain_text(&model.log_list[2]).contains("modified  hello.txt"));
+    }
+
+    #[test]
+    fn range_diff_keeps_removed_changes_in_stack_order() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.compare_operation();
+        model.prompt.as_mut().unwrap().text_area = TextArea::new("@--");
+        model.submit_prompt().unwrap();
+
+        let lines: Vec<String> = model.log_list.iter().map(plain_text).collect();
+        assert_eq!(lines[1], "  ▸ qpvuntsm changed   Add greeting");
+        assert_eq!(lines[2], "  ▸ ysrnknol removed   Dropped change");
+        assert_eq!(lines[3], "  ▸ mzvwutvl changed   (no description set)");
+    }
+
+    #[test]
+    fn range_diff_that_no_longer_loads_is_closed_with_the_reason() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        let revset = "trunk()..qpvuntsm".to_string();
+        model
+            .jj_log
+            .set_range_diff(Some(("0123abcd".to_string(), revset)));
+        model.reload().unwrap();
+        assert!(!model.jj_log.has_comparisons());
+        assert_eq!(
+            plain_text(model.info_list.as_ref().unwrap()),
+            "Closed the range-diff of trunk()..qpvuntsm at operation 0123abcd: \
+             Error: No operation ID matching \"0123abcd\""
+        );
+    }
+
+    #[test]
+    fn comparison_that_no_longer_resolves_is_closed_with_the_reason() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model
+            .jj_log
+            .set_comparison(Some(("ysrnknol".to_string(), "@".to_string())));
+        model.reload().unwrap();
+        assert!(!model.jj_log.has_comparisons());
+        assert_eq!(
+            plain_text(model.info_list.as_ref().unwrap()),
+            "Closed the comparison of ysrnknol → @: Error: Revision `ysrnknol` doesn't exist"
+        );
+    }
+
+    #[test]
+    fn absorb_previews_the_destination_of_each_hunk() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.select_next_node();
+        model.jj_absorb().unwrap();
+        let info = plain_text(model.info_list.as_ref().unwrap());
+        assert!(info.contains("jj absorb --from qpvuntsm hello.txt"));
+        assert!(info.contains("hello.txt:1 (-1 +1) → stays (lines from an immutable change)"));
+        assert!(info.contains("hello.txt:2 (-1 +1) → ysrnknol\n"));
+        assert!(info.contains("hello.txt:4 (-0 +1) → stays (only adds lines)"));
+        assert!(!info.contains("not just the selected hunk"));
+        assert!(model.confirmation.is_some());
+
+        model.cancel_confirmation();
+        model.toggle_current_fold().unwrap();
+        model.select_next_node();
+        model.jj_absorb().unwrap();
+        let info = plain_text(model.info_list.as_ref().unwrap());
+        assert!(info.contains("Absorbs all of hello.txt, not just the selected hunk"));
+    }
+
+    #[test]
+    fn absorb_from_a_merge_asks_without_a_preview() {
+        let (mut model, executor) = load_model("two_commits.txt");
+
+        model.log_select(model.jj_log.find_commit("rlvkpnrz").unwrap().flat_log_idx);
+        model.jj_absorb().unwrap();
+        let info = plain_text(model.info_list.as_ref().unwrap());
+        assert!(info.contains("jj absorb --from rlvkpnrz"));
+        assert!(info.contains("Can't preview absorbing a merge"));
+        assert!(model.confirmation.is_some());
+        assert!(
+            !executor
+                .calls()
+                .iter()
+                .any(|call| call.contains(&"annotate".to_string()))
+        );
+    }
+
+    #[test]
+    fn successful_command_shows_output_and_reloads_log() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.jj_new().unwrap();
+        assert!(plain_text(model.info_list.as_ref().unwrap()).contains("Running..."));
+        model.handle_jj_command_queue().unwrap();
+
+        let info = plain_text(model.info_list.as_ref().unwrap());
+        assert!(info.contains("jj new qpvuntsm"));
+        assert!(info.contains("Working copy  (@) now at: wqnwkozp"));
+        assert!(selected_text(&model).contains("wqnwkozp"));
+        assert_eq!(model.jj_log.log_tree.len(), 4);
+    }
+
+    #[test]
+    fn quick_describe_keeps_the_description_body() {
+        let (mut model, executor) = load_model("two_commits.txt");
+
+        model.jj_describe_quick().unwrap();
+        let prompt = model.prompt.as_mut().unwrap();
+        assert_eq!(prompt.text_area.text(), "Add greeting");
+        prompt.text_area = TextArea::new("Greet the world");
+        model.submit_prompt().unwrap();
+        model.handle_jj_command_queue().unwrap();
+
+        let describe_call = executor
+            .calls()
+            .into_iter()
+            .find(|call| call.contains(&"describe".to_string()))
+            .unwrap();
+        assert!(describe_call.ends_with(&[
+            "--message".to_string(),
+            "Greet the world\n\nSays hello to the world.".to_string()
+        ]));
+    }
+
+    #[test]
+    fn next_selects_the_new_working_copy() {
+        let (mut model, _) = load_model("two_commits.txt");
+
+        model.select_current_next_sibling_node().unwrap();
+        model.jj_next(false).unwrap();
+        model.handle_jj_command_queue().unwrap();
+
+        assert!(plain_text(model.info_list.as_ref().unwrap()).contains("jj next 1"));
+        assert!(selected_text(&model).contains("wqnwkozp"));
+    }
+
+    #[test]
+    fn failed_command_shows_stderr_without_reloading() {
+        let (mut model, executor) = load_model("two_commits.txt");
+
+        model.select_current_next_sibling_node().unwrap();
+        model.jj_edit().unwrap();
+        model.handle_jj_command_queue().unwrap();
+
+        let info = plain_text(model.info_list.as_ref().unwrap());
+        assert!(info.contains("jj edit rlvkpnrz"));
+        assert!(info.contains("Error: Commit 9f8e7d6c is immutable"));
+        let num_log_calls = executor
+            .calls()
+            .iter()
+            .filter(|call| call.contains(&"log".to_string()))
+            .count();
+        assert_eq!(num_log_calls, 1);
+        assert!(selected_text(&model).contains("rlvkpnrz"));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{text_area::TextArea, update::Message};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use std::str::FromStr;
+
+// Full-screen viewer for command output that would otherwise go to jj's pager
+#[derive(Debug)]
+pub struct Pager {
+    title: String,
+    text: Text<'static>,
+    // Lines without styling, for searching
+    plain_lines: Vec<String>,
+    scroll: usize,
+    height: usize,
+    search_input: Option<TextArea>,
+    query: Option<String>,
+    matches: Vec<usize>,
+    current_match: usize,
+    // Change ids the lines come from. If set, a cursor line is shown and Enter jumps to its change.
+    line_targets: Vec<Option<String>>,
+    cursor: usize,
+}
+
+impl Pager {
+    pub fn new(title: String, text: Text<'static>) -> Self {
+        let plain_lines = text
+            .lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect();
+        Self {
+            title,
+            text,
+            plain_lines,
+            scroll: 0,
+            height: 0,
+            search_input: None,
+            query: None,
+            matches: Vec::new(),
+            current_match: 0,
+            line_targets: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    pub fn with_line_targets(mut self, line_targets: Vec<Option<String>>) -> Self {
+        self.line_targets = line_targets;
+        self
+    }
+
+    fn has_cursor(&self) -> bool {
+        !self.line_targets.is_empty()
+    }
+
+    pub fn selected_target(&self) -> Option<&str> {
+        self.line_targets.get(self.cursor)?.as_deref()
+    }
+
+    // The scroll is clamped once the height is known on render
+    pub fn scrolled_to_bottom(mut self) -> Self {
+        self.scroll = usize::MAX;
+        self
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Message> {
+        if let Some(search_input) = &mut self.search_input {
+            match key.code {
+                KeyCode::Enter => {
+                    let query = search_input.text();
+                    self.search_input = None;
+                    self.search(query);
+                }
+                KeyCode::Esc => self.search_input = None,
+                _ => {
+                    search_input.handle_key(key);
+                }
+            }
+            return None;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let page = self.height.max(1) as isize;
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Some(Message::Quit),
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Message::ClosePager),
+            KeyCode::Enter if self.has_cursor() => return Some(Message::JumpToPagerTarget),
+            KeyCode::Char('f') if ctrl => self.move_by(page),
+            KeyCode::Char('b') if ctrl => self.move_by(-page),
+            KeyCode::Char('d') if ctrl => self.move_by(page / 2),
+            KeyCode::Char('u') if ctrl => self.move_by(-page / 2),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') | KeyCode::Char('f') => self.move_by(page),
+            KeyCode::PageUp | KeyCode::Char('b') => self.move_by(-page),
+            KeyCode::Char('d') => self.move_by(page / 2),
+            KeyCode::Char('u') => self.move_by(-page / 2),
+            KeyCode::Home | KeyCode::Char('g') => self.move_to(0),
+            KeyCode::End | KeyCode::Char('G') => self.move_to(usize::MAX),
+            KeyCode::Char('/') => self.search_input = Some(TextArea::new("")),
+            KeyCode::Char('n') => self.select_match(1),
+            KeyCode::Char('N') => self.select_match(-1),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn scroll_by(&mut self, lines: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(lines)
+            .min(self.max_scroll());
+    }
+
+    // Moves the cursor if there is one, scrolling to keep it visible, and scrolls otherwise
+    fn move_by(&mut self, lines: isize) {
+        if self.has_cursor() {
+            self.move_to(self.cursor.saturating_add_signed(lines));
+        } else {
+            self.scroll_by(lines);
+        }
+    }
+
+    fn move_to(&mut self, line_idx: usize) {
+        if !self.has_cursor() {
+            self.scroll = line_idx.min(self.max_scroll());
+            return;
+        }
+        self.cursor = line_idx.min(self.text.lines.len().saturating_sub(1));
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + self.height.max(1) {
+            self.scroll = self.cursor + 1 - self.height.max(1);
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.text.lines.len().saturating_sub(self.height)
+    }
+
+    // Smart case: the search is case sensitive only if the query has an uppercase letter
+    fn search(&mut self, query: String) {
+        if query.is_empty() {
+            self.query = None;
+            self.matches.clear();
+            return;
+        }
+
+        let case_sensitive = query.chars().any(char::is_uppercase);
+        let needle = if case_sensitive {
+            query.clone()
+        } else {
+            query.to_lowercase()
+        };
+        self.matches = self
+            .plain_lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                if case_sensitive {
+                    line.contains(&needle)
+                } else {
+                    line.to_lowercase().contains(&needle)
+                }
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        self.query = Some(query);
+
+        // Start from the first match at or after the top of the screen
+        self.current_match = self
+            .matches
+            .iter()
+            .position(|&idx| idx >= self.scroll)
+            .unwrap_or(0);
+        self.scroll_to_current_match();
+    }
+
+    fn select_match(&mut self, offset: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let num_matches = self.matches.len() as isize;
+        self.current_match =
+            (self.current_match as isize + offset).rem_euclid(num_matches) as usize;
+        self.scroll_to_current_match();
+    }
+
+    fn scroll_to_current_match(&mut self) {
+        if let Some(&line_idx) = self.matches.get(self.current_match) {
+            self.scroll = line_idx.min(self.max_scroll());
+            if self.has_cursor() {
+                self.cursor = line_idx;
+            }
+        }
+    }
+
+    // Returns the visible lines, the status line, and the cursor column if a search is being typed
+    pub fn render(&mut self, height: usize) -> (Text<'static>, Line<'static>, Option<u16>) {
+        self.height = height;
+        self.scroll = self.scroll.min(self.max_scroll());
+        // Scrolling with the mouse drags the cursor along
+        if self.has_cursor() {
+            let last_visible = (self.scroll + height).saturating_sub(1);
+            self.cursor = self
+                .cursor
+                .clamp(self.scroll, last_visible.max(self.scroll));
+        }
+
+        let match_style = Style::default().bg(Color::from_str("#44475A").unwrap());
+        let current_match_style = Style::default().bg(Color::from_str("#6272A4").unwrap());
+        let current_match_line = self.matches.get(self.current_match).copied();
+        let cursor_line = self.has_cursor().then_some(self.cursor);
+        let cursor_style = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .bg(Color::from_str("#282A36").unwrap());
+
+        let lines: Vec<Line> = self
+            .text
+            .lines
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .map(|(idx, line)| {
+                let line = if cursor_line == Some(idx) {
+                    line.clone().patch_style(cursor_style)
+                } else {
+                    line.clone()
+                };
+                if current_match_line == Some(idx) {
+                    line.patch_style(current_match_style)
+                } else if self.matches.binary_search(&idx).is_ok() {
+                    line.patch_style(match_style)
+                } else {
+                    line
+                }
+            })
+            .collect();
+
+        if let Some(search_input) = &mut self.search_input {
+            let (text, (cursor_x, _)) = search_input.render(1);
+            let mut status = Line::from(Span::styled("/", Style::default().fg(Color::Blue)));
+            if let Some(text_line) = text.lines.into_iter().next() {
+                status.extend(text_line.spans);
+            }
+            return (Text::from(lines), status, Some(cursor_x + 1));
+        }
+
+        let num_lines = self.text.lines.len();
+        let last_visible = (self.scroll + height).min(num_lines);
+        let mut status = Line::from(vec![
+            Span::styled(self.title.clone(), Style::default().fg(Color::Blue)),
+            Span::raw("  "),
+            Span::styled(
+                format!("lines {}-{last_visible}/{num_lines}", self.scroll + 1),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]);
+        if let Some(query) = &self.query {
+            let match_count = if self.matches.is_empty() {
+                "no matches".to_string()
+            } else {
+                format!("{}/{}", self.current_match + 1, self.matches.len())
+            };
+            status.push_span(Span::raw("  "));
+            status.push_span(Span::styled(
+                format!("/{query}"),
+                Style::default().fg(Color::Green),
+            ));
+            status.push_span(Span::styled(
+                format!(" [{match_count}]"),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if self.has_cursor() {
+            status.push_span(Span::raw("  "));
+            status.push_span(Span::styled("Enter", Style::default().fg(Color::Green)));
+            status.push_span(Span::raw(" go to change"));
+        }
+        status.push_span(Span::raw("  "));
+        status.push_span(Span::styled("q", Style::default().fg(Color::Green)));
+        status.push_span(Span::raw(" close"));
+
+        (Text::from(lines), status, None)
+    }
+}
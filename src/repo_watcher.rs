@@ -0,0 +1,195 @@
+use crate::{
+    command_log::CommandLogEntry,
+    executor::{JjExecutor, ProcessExecutor},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Reading the op heads directory is cheap, so it's checked often
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Running jj to snapshot the working copy isn't, so it's done less often
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5);
+// Changes must settle for this long before refreshing, so a burst of operations refreshes once
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub enum RepoEvent {
+    // The op heads changed and have settled
+    OpHeads(Vec<String>),
+    Snapshot(CommandLogEntry),
+}
+
+// Detects changes made to the repo outside majjit by polling the operation heads, which change with
+// every jj operation. Edits to working copy files only show up there once jj snapshots them, so a
+// snapshot is also taken periodically. Both happen on a worker thread, so a slow snapshot of a
+// large working copy doesn't hold up the UI.
+#[derive(Debug)]
+pub struct RepoWatcher {
+    op_heads_dir: PathBuf,
+    seen_op_heads: Vec<String>,
+    receiver: Receiver<RepoEvent>,
+}
+
+impl RepoWatcher {
+    // None if the repo's store can't be found, like for a non-default storage backend. The
+    // snapshot command is given as the global flags and args to run jj with.
+    pub fn new(repository: &str, snapshot_command: (Vec<String>, Vec<String>)) -> Option<Self> {
+        let repo_path = Path::new(repository).join(".jj").join("repo");
+        // Secondary workspaces have a file pointing at the main workspace's repo instead
+        let repo_dir = if repo_path.is_file() {
+            let target = fs::read_to_string(&repo_path).ok()?;
+            repo_path.parent()?.join(target.trim())
+        } else {
+            repo_path
+        };
+
+        let op_heads_dir = repo_dir.join("op_heads").join("heads");
+        let seen_op_heads = read_op_heads(&op_heads_dir)?;
+        let (sender, receiver) = mpsc::channel();
+        let worker_op_heads_dir = op_heads_dir.clone();
+        let worker_op_heads = seen_op_heads.clone();
+        thread::spawn(move || {
+            watch(
+                worker_op_heads_dir,
+                worker_op_heads,
+                snapshot_command,
+                sender,
+            )
+        });
+
+        Some(Self {
+            op_heads_dir,
+            seen_op_heads,
+            receiver,
+        })
+    }
+
+    // Called after majjit itself reloads the log, so its own operations don't trigger a refresh
+    pub fn mark_seen(&mut self) {
+        if let Some(op_heads) = read_op_heads(&self.op_heads_dir) {
+            self.seen_op_heads = op_heads;
+        }
+    }
+
+    // Returns whether the repo has changed since it was last seen, and the snapshots taken since
+    // the last check
+    pub fn check(&mut self) -> (bool, Vec<CommandLogEntry>) {
+        let mut changed = false;
+        let mut snapshots = Vec::new();
+        for event in self.receiver.try_iter() {
+            match event {
+                RepoEvent::OpHeads(op_heads) => {
+                    if op_heads != self.seen_op_heads {
+                        changed = true;
+                        self.seen_op_heads = op_heads;
+                    }
+                }
+                RepoEvent::Snapshot(entry) => snapshots.push(entry),
+            }
+        }
+        (changed, snapshots)
+    }
+}
+
+// Runs until the watcher is dropped, which is noticed the next time an event can't be sent
+fn watch(
+    op_heads_dir: PathBuf,
+    mut sent_op_heads: Vec<String>,
+    (global_flags, args): (Vec<String>, Vec<String>),
+    sender: Sender<RepoEvent>,
+) {
+    let mut pending: Option<(Vec<String>, Instant)> = None;
+    let mut last_snapshot = Instant::now();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        if let Some(op_heads) = read_op_heads(&op_heads_dir) {
+            match &pending {
+                _ if op_heads == sent_op_heads => pending = None,
+                Some((pending_op_heads, since))
+                    if *pending_op_heads == op_heads && since.elapsed() >= DEBOUNCE =>
+                {
+                    pending = None;
+                    sent_op_heads = op_heads.clone();
+                    if sender.send(RepoEvent::OpHeads(op_heads)).is_err() {
+                        return;
+                    }
+                }
+                Some((pending_op_heads, _)) if *pending_op_heads == op_heads => {}
+                _ => pending = Some((op_heads, Instant::now())),
+            }
+        }
+
+        if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+            let start = Instant::now();
+            let entry = match ProcessExecutor.run(&global_flags, &args) {
+                Ok(output) => CommandLogEntry {
+                    args: args.clone(),
+                    exit_code: output.exit_code,
+                    duration: start.elapsed(),
+                    stdout: output.stdout,
+                    stderr: output.stderr,
+                },
+                Err(err) => CommandLogEntry {
+                    args: args.clone(),
+                    exit_code: None,
+                    duration: start.elapsed(),
+                    stdout: String::new(),
+                    stderr: err.to_string(),
+                },
+            };
+            last_snapshot = Instant::now();
+            if sender.send(RepoEvent::Snapshot(entry)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// Each head is a file named after the operation id
+fn read_op_heads(op_heads_dir: &Path) -> Option<Vec<String>> {
+    let mut op_heads: Vec<String> = fs::read_dir(op_heads_dir)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().into_owned()))
+        .collect();
+    op_heads.sort();
+    Some(op_heads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn reports_op_heads_changed_outside_majjit_once() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let repository = std::env::temp_dir().join(format!("majjit-watch-{nanos}"));
+        let op_heads_dir = repository.join(".jj/repo/op_heads/heads");
+        fs::create_dir_all(&op_heads_dir).unwrap();
+        fs::write(op_heads_dir.join("a"), "").unwrap();
+
+        let no_snapshot = (Vec::new(), vec!["--version".to_string()]);
+        let mut watcher = RepoWatcher::new(repository.to_str().unwrap(), no_snapshot).unwrap();
+        fs::remove_file(op_heads_dir.join("a")).unwrap();
+        fs::write(op_heads_dir.join("b"), "").unwrap();
+
+        let start = Instant::now();
+        let mut num_changes = 0;
+        while start.elapsed() < POLL_INTERVAL * 4 {
+            thread::sleep(POLL_INTERVAL / 4);
+            let (changed, _) = watcher.check();
+            num_changes += changed as usize;
+        }
+        assert_eq!(num_changes, 1);
+
+        fs::remove_dir_all(repository).ok();
+    }
+}
@@ -4,7 +4,7 @@ use crossterm::{
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
+use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend, layout::Rect};
 use std::{
     cell::RefCell,
     io::{Stdout, stdout},
@@ -24,6 +24,16 @@ pub fn init_terminal() -> Result<Term> {
     Ok(terminal)
 }
 
+// Stands in for the real terminal when running headless. It can't be drawn to, so the model
+// refuses commands that would hand the terminal over to jj or an editor.
+pub fn detached_terminal() -> Result<Term> {
+    let options = TerminalOptions {
+        viewport: Viewport::Fixed(Rect::ZERO),
+    };
+    let terminal = Terminal::with_options(CrosstermBackend::new(stdout()), options)?;
+    Ok(Rc::new(RefCell::new(terminal)))
+}
+
 pub fn takeover_terminal(terminal: Term) -> Result<()> {
     enable_raw_mode()?;
     execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
@@ -0,0 +1,192 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span, Text},
+};
+
+#[derive(Debug, Clone)]
+pub struct TextArea {
+    lines: Vec<String>,
+    cursor_row: usize,
+    cursor_col: usize,
+    scroll: usize,
+    // Soft limits for the first line and the remaining lines, past which text is highlighted
+    line_length_guides: Option<(usize, usize)>,
+}
+
+impl TextArea {
+    pub fn new(text: &str) -> Self {
+        let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        let cursor_row = lines.len() - 1;
+        let cursor_col = lines[cursor_row].chars().count();
+
+        Self {
+            lines,
+            cursor_row,
+            cursor_col,
+            scroll: 0,
+            line_length_guides: None,
+        }
+    }
+
+    pub fn with_line_length_guides(mut self, first_line: usize, other_lines: usize) -> Self {
+        self.line_length_guides = Some((first_line, other_lines));
+        self
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn num_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    // Returns true if the key was handled
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.cursor_col = 0,
+            KeyCode::Char('e') if ctrl => self.cursor_col = self.line_len(),
+            KeyCode::Char('k') if ctrl => {
+                let byte_idx = self.byte_idx(self.cursor_col);
+                self.lines[self.cursor_row].truncate(byte_idx);
+            }
+            KeyCode::Char(_) if ctrl => return false,
+            KeyCode::Char(c) => {
+                let byte_idx = self.byte_idx(self.cursor_col);
+                self.lines[self.cursor_row].insert(byte_idx, c);
+                self.cursor_col += 1;
+            }
+            KeyCode::Enter => {
+                let byte_idx = self.byte_idx(self.cursor_col);
+                let rest = self.lines[self.cursor_row].split_off(byte_idx);
+                self.cursor_row += 1;
+                self.cursor_col = 0;
+                self.lines.insert(self.cursor_row, rest);
+            }
+            KeyCode::Backspace => {
+                if self.cursor_col > 0 {
+                    self.cursor_col -= 1;
+                    let byte_idx = self.byte_idx(self.cursor_col);
+                    self.lines[self.cursor_row].remove(byte_idx);
+                } else if self.cursor_row > 0 {
+                    let line = self.lines.remove(self.cursor_row);
+                    self.cursor_row -= 1;
+                    self.cursor_col = self.line_len();
+                    self.lines[self.cursor_row].push_str(&line);
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor_col < self.line_len() {
+                    let byte_idx = self.byte_idx(self.cursor_col);
+                    self.lines[self.cursor_row].remove(byte_idx);
+                } else if self.cursor_row < self.lines.len() - 1 {
+                    let line = self.lines.remove(self.cursor_row + 1);
+                    self.lines[self.cursor_row].push_str(&line);
+                }
+            }
+            KeyCode::Left => {
+                if self.cursor_col > 0 {
+                    self.cursor_col -= 1;
+                } else if self.cursor_row > 0 {
+                    self.cursor_row -= 1;
+                    self.cursor_col = self.line_len();
+                }
+            }
+            KeyCode::Right => {
+                if self.cursor_col < self.line_len() {
+                    self.cursor_col += 1;
+                } else if self.cursor_row < self.lines.len() - 1 {
+                    self.cursor_row += 1;
+                    self.cursor_col = 0;
+                }
+            }
+            KeyCode::Up => {
+                self.cursor_row = self.cursor_row.saturating_sub(1);
+                self.cursor_col = self.cursor_col.min(self.line_len());
+            }
+            KeyCode::Down => {
+                self.cursor_row = (self.cursor_row + 1).min(self.lines.len() - 1);
+                self.cursor_col = self.cursor_col.min(self.line_len());
+            }
+            KeyCode::Home => self.cursor_col = 0,
+            KeyCode::End => self.cursor_col = self.line_len(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.cursor_row].chars().count()
+    }
+
+    fn byte_idx(&self, char_idx: usize) -> usize {
+        let line = &self.lines[self.cursor_row];
+        line.char_indices()
+            .nth(char_idx)
+            .map_or(line.len(), |(idx, _)| idx)
+    }
+
+    fn line_length_guide(&self, row: usize) -> Option<usize> {
+        let (first_line, other_lines) = self.line_length_guides?;
+        if row == 0 {
+            Some(first_line)
+        } else {
+            Some(other_lines)
+        }
+    }
+
+    // Keeps the cursor within the visible rows and returns the lines to display along with the
+    // cursor position relative to them
+    pub fn render(&mut self, height: usize) -> (Text<'static>, (u16, u16)) {
+        if self.cursor_row < self.scroll {
+            self.scroll = self.cursor_row;
+        } else if height > 0 && self.cursor_row >= self.scroll + height {
+            self.scroll = self.cursor_row + 1 - height;
+        }
+
+        let overflow_style = Style::default().fg(Color::LightRed);
+        let lines: Vec<Line> = self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .map(|(row, line)| match self.line_length_guide(row) {
+                Some(guide) if line.chars().count() > guide => {
+                    let split_idx = line.char_indices().nth(guide).unwrap().0;
+                    Line::from(vec![
+                        Span::raw(line[..split_idx].to_string()),
+                        Span::styled(line[split_idx..].to_string(), overflow_style),
+                    ])
+                }
+                _ => Line::raw(line.clone()),
+            })
+            .collect();
+
+        // Wide characters like CJK take two columns, so the cursor goes by display width
+        let line = &self.lines[self.cursor_row];
+        let cursor_x = Span::raw(&line[..self.byte_idx(self.cursor_col)]).width();
+        let cursor = (cursor_x as u16, (self.cursor_row - self.scroll) as u16);
+        (Text::from(lines), cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_column_counts_wide_characters_twice() {
+        let mut text_area = TextArea::new("日本 ok");
+        assert_eq!(text_area.render(1).1, (7, 0));
+
+        text_area.handle_key(KeyEvent::from(KeyCode::Home));
+        text_area.handle_key(KeyEvent::from(KeyCode::Right));
+        assert_eq!(text_area.render(1).1, (2, 0));
+    }
+}
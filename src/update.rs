@@ -1,4 +1,8 @@
-use crate::{model::Model, terminal::Term};
+use crate::{
+    jj_commands::Placement,
+    model::{Focus, Model, YankTarget},
+    terminal::Term,
+};
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers, MouseEventKind};
 use std::time::Duration;
@@ -22,53 +26,199 @@ pub enum Message {
     ScrollDownPage,
     ScrollUpPage,
     LeftMouseClick { row: u16, column: u16 },
+    LeftMouseDrag { row: u16, column: u16 },
     RightMouseClick { row: u16, column: u16 },
+    MiddleMouseClick { row: u16, column: u16 },
     Refresh,
     ToggleIgnoreImmutable,
+    ToggleDiffFormat,
+    IncreaseDiffContext,
+    DecreaseDiffContext,
+    ToggleIgnoreAllSpace,
+    ToggleIgnoreSpaceChange,
+    ResetDiffOptions,
+    ToggleSideBySide,
+    TogglePreview,
+    CycleFocus,
+    FocusLog,
+    ScrollInfoPanelDown,
+    ScrollInfoPanelUp,
+    ScrollInfoPanelDownPage,
+    ScrollInfoPanelUpPage,
+    ToggleInfoPanelMaximised,
+    ScrollPreviewDown,
+    ScrollPreviewUp,
+    ScrollPreviewDownPage,
+    ScrollPreviewUpPage,
     Show,
+    ClosePager,
+    JumpToPagerTarget,
+    ShowCommandLog,
     Describe,
+    DescribeInline,
+    DescribeQuick,
+    SaveDescription,
+    CancelDescription,
     New,
     NewBefore,
+    Next { edit: bool },
+    NextBy { edit: bool },
+    Prev { edit: bool },
+    PrevBy { edit: bool },
     Abandon,
+    Absorb,
     Undo,
     Commit,
+    CommitInline,
+    CommitQuick,
+    CompareMarked,
+    CompareTrunk,
+    CompareRemote,
+    CompareOperation,
+    CloseComparison,
+    SubmitPrompt,
+    CancelPrompt,
+    Confirm,
+    CancelConfirmation,
     Restore,
     Squash,
     Edit,
+    MarkChange,
+    Duplicate,
+    DuplicateOnto,
+    DuplicateAfter,
+    DuplicateBefore,
+    Revert,
+    RevertOnto,
+    RevertAfter,
+    RevertBefore,
+    Parallelize,
+    FileAnnotate,
     GitFetch,
     GitPush,
     BookmarkSetMaster,
+    YankChangeId,
+    YankCommitId,
+    YankFilePath,
+    YankDescription,
+    YankHunk,
+    YankFilePatch,
+    OpenInEditor,
+}
+
+// What a command acts on, for listing the commands that apply to a node
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CommandTarget {
+    Change,
+    File,
+}
+
+impl Message {
+    // None for commands that don't depend on the selection
+    pub fn command_target(&self) -> Option<CommandTarget> {
+        match self {
+            Message::Abandon
+            | Message::Absorb
+            | Message::BookmarkSetMaster
+            | Message::Commit
+            | Message::CommitInline
+            | Message::CommitQuick
+            | Message::CompareMarked
+            | Message::CompareRemote
+            | Message::CompareOperation
+            | Message::Describe
+            | Message::DescribeInline
+            | Message::DescribeQuick
+            | Message::Duplicate
+            | Message::DuplicateOnto
+            | Message::DuplicateAfter
+            | Message::DuplicateBefore
+            | Message::Edit
+            | Message::MarkChange
+            | Message::New
+            | Message::NewBefore
+            | Message::Parallelize
+            | Message::Restore
+            | Message::Revert
+            | Message::RevertOnto
+            | Message::RevertAfter
+            | Message::RevertBefore
+            | Message::Squash
+            | Message::YankChangeId
+            | Message::YankCommitId
+            | Message::YankDescription => Some(CommandTarget::Change),
+            Message::FileAnnotate
+            | Message::ToggleSideBySide
+            | Message::YankFilePath
+            | Message::YankHunk
+            | Message::YankFilePatch
+            | Message::OpenInEditor => Some(CommandTarget::File),
+            _ => None,
+        }
+    }
 }
 
 pub fn update(terminal: Term, model: &mut Model) -> Result<()> {
+    let maybe_event = if event::poll(EVENT_POLL_DURATION)? {
+        Some(event::read()?)
+    } else {
+        None
+    };
+    update_with_event(terminal, model, maybe_event)?;
+    model.check_repo_changes()
+}
+
+// Separate from polling so scripted events can be fed in when running headless
+pub fn update_with_event(
+    terminal: Term,
+    model: &mut Model,
+    maybe_event: Option<Event>,
+) -> Result<()> {
     model.handle_jj_command_queue()?;
+    model.handle_resize()?;
 
-    let mut current_msg = handle_event(model)?;
+    let mut current_msg = maybe_event.and_then(|event| handle_event(model, event));
     while let Some(msg) = current_msg {
         current_msg = handle_msg(terminal.clone(), model, msg)?;
     }
+    model.sync_preview()?;
 
     Ok(())
 }
 
-fn handle_event(model: &mut Model) -> Result<Option<Message>> {
-    if event::poll(EVENT_POLL_DURATION)? {
-        match event::read()? {
-            Event::Key(key) => {
-                if key.kind == event::KeyEventKind::Press {
-                    return Ok(handle_key(model, key));
-                }
+fn handle_event(model: &mut Model, event: Event) -> Option<Message> {
+    match event {
+        Event::Key(key) => {
+            if key.kind == event::KeyEventKind::Press {
+                handle_key(model, key)
+            } else {
+                None
             }
-            Event::Mouse(mouse) => {
-                return Ok(handle_mouse(mouse));
-            }
-            _ => {}
         }
+        Event::Mouse(mouse) => handle_mouse(model, mouse),
+        _ => None,
     }
-    Ok(None)
 }
 
 fn handle_key(model: &mut Model, key: event::KeyEvent) -> Option<Message> {
+    if model.pager.is_some() {
+        return model.handle_pager_key(key);
+    }
+    if model.description_editor.is_some() {
+        return model.handle_description_editor_key(key);
+    }
+    if model.prompt.is_some() {
+        return model.handle_prompt_key(key);
+    }
+    if model.confirmation.is_some() {
+        return model.handle_confirmation_key(key);
+    }
+    match model.focus {
+        Focus::Log => {}
+        Focus::Preview => return model.handle_preview_key(key),
+        Focus::InfoPanel => return model.handle_info_panel_key(key),
+    }
+
     match key.code {
         KeyCode::Char('q') => Some(Message::Quit),
         KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Message::Quit),
@@ -86,24 +236,44 @@ fn handle_key(model: &mut Model, key: event::KeyEvent) -> Option<Message> {
         KeyCode::Enter => Some(Message::Show),
         KeyCode::Esc => Some(Message::Clear),
         KeyCode::Char('@') => Some(Message::SelectCurrentWorkingCopy),
+        KeyCode::Char('m') => Some(Message::MarkChange),
         KeyCode::Char('i') => Some(Message::ToggleIgnoreImmutable),
+        KeyCode::Char('p') => Some(Message::TogglePreview),
+        KeyCode::Char('o') => Some(Message::CycleFocus),
+        KeyCode::Char('z') => Some(Message::ToggleInfoPanelMaximised),
         KeyCode::Char('?') => Some(Message::ShowHelp),
+        KeyCode::Char('$') => Some(Message::ShowCommandLog),
         _ => model.handle_command_key(key.code),
     }
 }
 
-fn handle_mouse(mouse: event::MouseEvent) -> Option<Message> {
+fn handle_mouse(model: &Model, mouse: event::MouseEvent) -> Option<Message> {
+    let (row, column) = (mouse.row, mouse.column);
+    // The wheel scrolls whichever pane is under the pointer
+    let pane = model.pane_at(row, column);
     match mouse.kind {
-        MouseEventKind::ScrollDown => Some(Message::ScrollDown),
-        MouseEventKind::ScrollUp => Some(Message::ScrollUp),
-        MouseEventKind::Down(event::MouseButton::Left) => Some(Message::LeftMouseClick {
-            row: mouse.row,
-            column: mouse.column,
+        MouseEventKind::ScrollDown => Some(match pane {
+            Some(Focus::InfoPanel) => Message::ScrollInfoPanelDown,
+            Some(Focus::Preview) => Message::ScrollPreviewDown,
+            _ => Message::ScrollDown,
         }),
-        MouseEventKind::Down(event::MouseButton::Right) => Some(Message::RightMouseClick {
-            row: mouse.row,
-            column: mouse.column,
+        MouseEventKind::ScrollUp => Some(match pane {
+            Some(Focus::InfoPanel) => Message::ScrollInfoPanelUp,
+            Some(Focus::Preview) => Message::ScrollPreviewUp,
+            _ => Message::ScrollUp,
         }),
+        MouseEventKind::Down(event::MouseButton::Left) => {
+            Some(Message::LeftMouseClick { row, column })
+        }
+        MouseEventKind::Drag(event::MouseButton::Left) => {
+            Some(Message::LeftMouseDrag { row, column })
+        }
+        MouseEventKind::Down(event::MouseButton::Right) => {
+            Some(Message::RightMouseClick { row, column })
+        }
+        MouseEventKind::Down(event::MouseButton::Middle) => {
+            Some(Message::MiddleMouseClick { row, column })
+        }
         _ => None,
     }
 }
@@ -117,6 +287,29 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
         Message::ShowHelp => model.show_help(),
         Message::Quit => model.quit(),
 
+        // Diff options
+        Message::ToggleDiffFormat => model.toggle_diff_format()?,
+        Message::IncreaseDiffContext => model.increase_diff_context()?,
+        Message::DecreaseDiffContext => model.decrease_diff_context()?,
+        Message::ToggleIgnoreAllSpace => model.toggle_ignore_all_space()?,
+        Message::ToggleIgnoreSpaceChange => model.toggle_ignore_space_change()?,
+        Message::ResetDiffOptions => model.reset_diff_options()?,
+        Message::ToggleSideBySide => model.toggle_side_by_side()?,
+
+        // Panes
+        Message::CycleFocus => model.cycle_focus(),
+        Message::FocusLog => model.focus_log(),
+        Message::ScrollInfoPanelDown => model.scroll_info_panel(1),
+        Message::ScrollInfoPanelUp => model.scroll_info_panel(-1),
+        Message::ScrollInfoPanelDownPage => model.scroll_info_panel_page(1),
+        Message::ScrollInfoPanelUpPage => model.scroll_info_panel_page(-1),
+        Message::ToggleInfoPanelMaximised => model.toggle_info_panel_maximised(),
+        Message::TogglePreview => model.toggle_preview(),
+        Message::ScrollPreviewDown => model.scroll_preview(1),
+        Message::ScrollPreviewUp => model.scroll_preview(-1),
+        Message::ScrollPreviewDownPage => model.scroll_preview_page(1),
+        Message::ScrollPreviewUpPage => model.scroll_preview_page(-1),
+
         // Navigation
         Message::ScrollDownPage => model.scroll_down_page(),
         Message::ScrollUpPage => model.scroll_up_page(),
@@ -127,30 +320,80 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
         Message::SelectParentNode => model.select_parent_node()?,
         Message::SelectCurrentWorkingCopy => model.select_current_working_copy(),
         Message::Show => model.jj_show(term)?,
+        Message::ClosePager => model.close_pager(),
+        Message::JumpToPagerTarget => model.jump_to_pager_target()?,
+        Message::ShowCommandLog => model.show_command_log()?,
         Message::ToggleLogListFold => model.toggle_current_fold()?,
 
         // Mouse
         Message::ScrollDown => model.scroll_down_once(),
         Message::ScrollUp => model.scroll_up_once(),
-        Message::LeftMouseClick { row, column } => model.handle_mouse_click(row, column),
+        Message::LeftMouseClick { row, column } => {
+            return Ok(model.handle_mouse_click(row, column));
+        }
+        Message::LeftMouseDrag { row, column } => model.handle_mouse_drag(row, column),
         Message::RightMouseClick { row, column } => {
-            model.handle_mouse_click(row, column);
-            model.toggle_current_fold()?;
+            if model.select_clicked_node(row, column).is_some() {
+                model.toggle_current_fold()?;
+            }
+        }
+        Message::MiddleMouseClick { row, column } => {
+            if model.select_clicked_node(row, column).is_some() {
+                model.show_context_menu();
+            }
         }
 
         // Commands
         Message::Abandon => model.jj_abandon()?,
+        Message::Absorb => model.jj_absorb()?,
         Message::BookmarkSetMaster => model.jj_bookmark_set_master()?,
         Message::Commit => model.jj_commit(term)?,
+        Message::CommitInline => model.jj_commit_inline()?,
+        Message::CommitQuick => model.jj_commit_quick()?,
+        Message::CompareMarked => model.compare_marked()?,
+        Message::CompareTrunk => model.compare_trunk()?,
+        Message::CompareRemote => model.compare_remote()?,
+        Message::CompareOperation => model.compare_operation(),
+        Message::CloseComparison => model.close_comparison()?,
         Message::Describe => model.jj_describe(term)?,
+        Message::DescribeInline => model.jj_describe_inline()?,
+        Message::DescribeQuick => model.jj_describe_quick()?,
+        Message::SaveDescription => model.save_description()?,
+        Message::CancelDescription => model.cancel_description(),
+        Message::SubmitPrompt => model.submit_prompt()?,
+        Message::CancelPrompt => model.cancel_prompt(),
+        Message::Confirm => model.confirm()?,
+        Message::CancelConfirmation => model.cancel_confirmation(),
         Message::Edit => model.jj_edit()?,
+        Message::MarkChange => model.mark_change(),
+        Message::Duplicate => model.jj_duplicate()?,
+        Message::DuplicateOnto => model.jj_duplicate_to(Placement::Onto),
+        Message::DuplicateAfter => model.jj_duplicate_to(Placement::After),
+        Message::DuplicateBefore => model.jj_duplicate_to(Placement::Before),
+        Message::Revert => model.jj_revert()?,
+        Message::RevertOnto => model.jj_revert_to(Placement::Onto),
+        Message::RevertAfter => model.jj_revert_to(Placement::After),
+        Message::RevertBefore => model.jj_revert_to(Placement::Before),
+        Message::Parallelize => model.jj_parallelize()?,
+        Message::FileAnnotate => model.jj_file_annotate()?,
+        Message::OpenInEditor => model.open_in_editor(term)?,
         Message::GitFetch => model.jj_fetch()?,
         Message::GitPush => model.jj_push()?,
         Message::New => model.jj_new()?,
         Message::NewBefore => model.jj_new_before()?,
+        Message::Next { edit } => model.jj_next(edit)?,
+        Message::NextBy { edit } => model.jj_next_by(edit),
+        Message::Prev { edit } => model.jj_prev(edit)?,
+        Message::PrevBy { edit } => model.jj_prev_by(edit),
         Message::Restore => model.jj_restore()?,
         Message::Squash => model.jj_squash(term)?,
         Message::Undo => model.jj_undo()?,
+        Message::YankChangeId => model.yank(YankTarget::ChangeId)?,
+        Message::YankCommitId => model.yank(YankTarget::CommitId)?,
+        Message::YankFilePath => model.yank(YankTarget::FilePath)?,
+        Message::YankDescription => model.yank(YankTarget::Description)?,
+        Message::YankHunk => model.yank(YankTarget::Hunk)?,
+        Message::YankFilePatch => model.yank(YankTarget::FilePatch)?,
     };
 
     Ok(None)
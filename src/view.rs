@@ -1,16 +1,39 @@
 use std::str::FromStr;
 
-use crate::model::Model;
+use crate::{
+    jj_commands::DiffOptions,
+    model::{DescriptionTarget, Focus, Model},
+};
 
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Position},
     style::{Color, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, List, Paragraph},
+    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph},
 };
 
+const DESCRIPTION_EDITOR_MIN_HEIGHT: u16 = 6;
+// Below this width the preview pane goes under the log instead of beside it
+const PREVIEW_SIDE_MIN_WIDTH: u16 = 160;
+// Lines of the log left visible when the info panel is maximised
+const LOG_MIN_HEIGHT: u16 = 3;
+
 pub fn view(model: &mut Model, frame: &mut Frame) {
+    if let Some(pager) = &mut model.pager {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(frame.area());
+        let (text, status, maybe_cursor_x) = pager.render(layout[0].height as usize);
+        frame.render_widget(Paragraph::new(text), layout[0]);
+        frame.render_widget(Paragraph::new(status), layout[1]);
+        if let Some(cursor_x) = maybe_cursor_x {
+            frame.set_cursor_position(Position::new(layout[1].x + cursor_x, layout[1].y));
+        }
+        return;
+    }
+
     let mut header_spans = vec![
         Span::styled("repository: ", Style::default().fg(Color::Blue)),
         Span::styled(
@@ -21,6 +44,15 @@ pub fn view(model: &mut Model, frame: &mut Frame) {
         Span::styled("revset: ", Style::default().fg(Color::Blue)),
         Span::styled(&model.revset, Style::default().fg(Color::Green)),
     ];
+    let diff_options = &model.global_args.diff_options;
+    if *diff_options != DiffOptions::default() {
+        header_spans.push(Span::raw("  "));
+        header_spans.push(Span::styled("diff: ", Style::default().fg(Color::Blue)));
+        header_spans.push(Span::styled(
+            diff_options.to_args().join(" "),
+            Style::default().fg(Color::Green),
+        ));
+    }
     if model.global_args.ignore_immutable {
         header_spans.push(Span::styled(
             "  --ignore-immutable",
@@ -29,8 +61,19 @@ pub fn view(model: &mut Model, frame: &mut Frame) {
     }
     let header = Paragraph::new(Line::from(header_spans));
 
-    let log_list = List::new(model.log_list.clone())
-        .highlight_style(Style::new().bold().bg(Color::from_str("#282A36").unwrap()))
+    let selection_bg = Color::from_str("#282A36").unwrap();
+    let line_range = model.selected_line_range();
+    let log_items = model.log_list.iter().enumerate().map(|(idx, text)| {
+        let item = ListItem::new(text.clone());
+        match &line_range {
+            Some(line_range) if line_range.contains(&idx) => {
+                item.style(Style::new().bg(selection_bg))
+            }
+            _ => item,
+        }
+    });
+    let log_list = List::new(log_items)
+        .highlight_style(Style::new().bold().bg(selection_bg))
         .scroll_padding(model.log_list_scroll_padding);
 
     let layout = Layout::default()
@@ -38,25 +81,138 @@ pub fn view(model: &mut Model, frame: &mut Frame) {
         .constraints([
             Constraint::Length(2),
             Constraint::Min(0),
-            if let Some(info_list) = &model.info_list {
-                Constraint::Length(info_list.lines.len() as u16 + 2)
+            if let Some(editor) = &model.description_editor {
+                let height = (editor.text_area.num_lines() as u16 + 2)
+                    .max(DESCRIPTION_EDITOR_MIN_HEIGHT)
+                    .min(frame.area().height / 2);
+                Constraint::Length(height)
+            } else if let Some(info_list) = &model.info_list {
+                let max_height = if model.info_panel.maximised {
+                    frame.area().height.saturating_sub(2 + LOG_MIN_HEIGHT)
+                } else {
+                    frame.area().height / 3
+                };
+                Constraint::Length(
+                    u16::try_from(info_list.lines.len())
+                        .unwrap_or(u16::MAX)
+                        .saturating_add(2)
+                        .min(max_height),
+                )
             } else {
                 Constraint::Length(0)
             },
+            Constraint::Length(if model.prompt.is_some() { 1 } else { 0 }),
         ])
         .split(frame.area());
 
     frame.render_widget(header, layout[0]);
-    frame.render_stateful_widget(log_list, layout[1], &mut model.log_list_state);
-    model.log_list_layout = layout[1];
-
-    if let Some(info_list) = &model.info_list {
-        let info_list = List::new(info_list.clone()).block(
-            Block::default()
-                .borders(Borders::TOP)
-                .border_type(BorderType::Thick)
-                .border_style(Style::default().fg(Color::DarkGray)),
-        );
-        frame.render_widget(info_list, layout[2]);
+
+    let log_list_area = match &mut model.preview {
+        None => layout[1],
+        Some(preview) => {
+            let (direction, borders) = if layout[1].width >= PREVIEW_SIDE_MIN_WIDTH {
+                (Direction::Horizontal, Borders::LEFT)
+            } else {
+                (Direction::Vertical, Borders::TOP)
+            };
+            let panes = Layout::default()
+                .direction(direction)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(layout[1]);
+
+            let border_color = if model.focus == Focus::Preview {
+                Color::Blue
+            } else {
+                Color::DarkGray
+            };
+            let block = Block::default()
+                .borders(borders)
+                .border_style(Style::default().fg(border_color))
+                .title(Span::styled(" Preview ", Style::default().fg(border_color)));
+            let preview_widget = Paragraph::new(preview.text.clone())
+                .block(block)
+                .scroll((preview.scroll, 0));
+            frame.render_widget(preview_widget, panes[1]);
+            preview.layout = panes[1];
+
+            panes[0]
+        }
+    };
+    frame.render_stateful_widget(log_list, log_list_area, &mut model.log_list_state);
+    model.log_list_layout = log_list_area;
+
+    if let Some(editor) = &mut model.description_editor {
+        let title = match &editor.target {
+            DescriptionTarget::Describe { change_id } => format!(" Describe {change_id} "),
+            DescriptionTarget::Commit => " Commit @ ".to_string(),
+        };
+        let block = Block::default()
+            .borders(Borders::TOP)
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title(Span::styled(title, Style::default().fg(Color::Blue)))
+            .title_bottom(Line::from(vec![
+                Span::styled(" Ctrl-s", Style::default().fg(Color::Green)),
+                Span::raw(" save  "),
+                Span::styled("Esc", Style::default().fg(Color::Green)),
+                Span::raw(" cancel "),
+            ]));
+        let inner_area = block.inner(layout[2]);
+        let (text, (cursor_x, cursor_y)) = editor.text_area.render(inner_area.height as usize);
+        frame.render_widget(Paragraph::new(text).block(block), layout[2]);
+        frame.set_cursor_position(Position::new(
+            inner_area.x + cursor_x,
+            inner_area.y + cursor_y,
+        ));
+    } else if let Some(info_list) = &model.info_list {
+        let info_panel = &mut model.info_panel;
+        info_panel.layout = layout[2];
+
+        let border_color = if model.focus == Focus::InfoPanel {
+            Color::Blue
+        } else {
+            Color::DarkGray
+        };
+        let mut block = Block::default()
+            .borders(Borders::TOP)
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(border_color));
+
+        // Only show the position when some of the output doesn't fit
+        let num_lines = u16::try_from(info_list.lines.len()).unwrap_or(u16::MAX);
+        let visible_lines = layout[2].height.saturating_sub(1);
+        if num_lines > visible_lines {
+            info_panel.scroll = info_panel.scroll.min(num_lines - visible_lines);
+            let first_visible = info_panel.scroll + 1;
+            let last_visible = info_panel.scroll + visible_lines;
+            block = block.title(
+                Line::from(Span::styled(
+                    format!(" {first_visible}-{last_visible}/{num_lines} "),
+                    Style::default().fg(border_color),
+                ))
+                .right_aligned(),
+            );
+        }
+
+        let info_paragraph = Paragraph::new(info_list.clone())
+            .block(block)
+            .scroll((info_panel.scroll, 0));
+        frame.render_widget(info_paragraph, layout[2]);
+    }
+    if let Some(prompt) = &mut model.prompt {
+        let label_width = Span::raw(&prompt.label).width() as u16;
+        let (text, (cursor_x, _)) = prompt.text_area.render(1);
+        let mut line = Line::from(Span::styled(
+            prompt.label.clone(),
+            Style::default().fg(Color::Blue),
+        ));
+        if let Some(text_line) = text.lines.into_iter().next() {
+            line.extend(text_line.spans);
+        }
+        frame.render_widget(Paragraph::new(line), layout[3]);
+        frame.set_cursor_position(Position::new(
+            layout[3].x + label_width + cursor_x,
+            layout[3].y,
+        ));
     }
 }